@@ -18,6 +18,10 @@ pub async fn addboard(
     #[min = 1]
     #[max = 50]
     min_reactions: Option<i64>,
+    #[description = "Remove posted messages that fall below this many reactions (0 to never remove)"]
+    #[min = 0]
+    #[max = 50]
+    remove_below: Option<i64>,
 ) -> Result<(), Error> {
     let guild_id = ctx
         .guild_id()
@@ -29,19 +33,26 @@ pub async fn addboard(
         return Ok(());
     }
 
+    if remove_below.unwrap_or(0) > min_reactions.unwrap_or(5) {
+        ctx.say("The removal threshold can't be higher than the minimum number of reactions")
+            .await?;
+        return Ok(());
+    }
+
     match db::add_board(
         guild_id,
         &name,
         parsed_reactions,
         min_reactions,
         dest_channel.id,
+        remove_below,
     ) {
         Ok(()) => {
             ctx.say(format!(
                 "Board '{}' created successfully! Messages with {} or more reactions will be posted to <#{}>",
                 name,
                 min_reactions.unwrap_or(1),
-                dest_channel.id.to_string()
+                dest_channel.id
             )).await?;
         }
         Err(err) => {
@@ -0,0 +1,139 @@
+use crate::{Context, Error, commands::autocomplete_board_names, db};
+use poise::{ChoiceParameter, serenity_prelude as serenity};
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn boardchannels(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Include or exclude a channel or category as a source of board posts
+#[poise::command(slash_command, guild_only, owners_only)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+    #[description = "Channel or category"] channel: serenity::GuildChannel,
+    #[description = "Whether the board takes messages from the channel"] rule: db::ChannelRule,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    match db::set_board_channel(board.board_id, channel.id, rule) {
+        Ok(()) => {
+            ctx.say(format!(
+                "Board '{}' will {} <#{}>",
+                name,
+                rule.name(),
+                channel.id
+            ))
+            .await?;
+        }
+        Err(err) => {
+            ctx.say(format!("Failed to add channel: {}", err)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the rule of a channel or category
+#[poise::command(slash_command, guild_only, owners_only)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+    #[description = "Channel or category"] channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    match db::remove_board_channel(board.board_id, channel.id) {
+        Ok(true) => {
+            ctx.say(format!("Removed <#{}> from board '{}'", channel.id, name))
+                .await?;
+        }
+        Ok(false) => {
+            ctx.say(format!(
+                "Board '{}' has no rule for <#{}>",
+                name, channel.id
+            ))
+            .await?;
+        }
+        Err(err) => {
+            ctx.say(format!("Failed to remove channel: {}", err))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// List the channel and category rules of a board
+#[poise::command(slash_command, guild_only)]
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    let rules = db::get_board_channels(board.board_id)?;
+
+    // without include rules every channel but the board's own is a source
+    let mut lines = if rules
+        .iter()
+        .any(|(_, rule)| *rule == db::ChannelRule::Include)
+    {
+        vec!["Only included channels are sources".to_string()]
+    } else {
+        vec![format!(
+            "All channels except <#{}> and excluded ones are sources",
+            board.dest_channel
+        )]
+    };
+
+    for (channel_id, rule) in rules {
+        lines.push(format!("<#{}> → {}", channel_id, rule.name()));
+    }
+
+    ctx.say(format!("**{}** channels\n{}", board.name, lines.join("\n")))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use crate::{Context, Error, commands::autocomplete_board_names, db};
+use poise::{ChoiceParameter, serenity_prelude as serenity};
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn boardroles(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Exclude, require or weight the reactions of members with a role
+#[poise::command(slash_command, guild_only, owners_only)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+    #[description = "Role"] role: serenity::Role,
+    #[description = "How reactions from members with the role count"] rule: db::RoleRule,
+    #[description = "Reactions a reaction counts as, for weight rules"]
+    #[min = 1]
+    #[max = 10]
+    weight: Option<i64>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    let weight = match (rule, weight) {
+        (db::RoleRule::Weight, None) => {
+            ctx.say("Weight rules need a weight").await?;
+            return Ok(());
+        }
+        (db::RoleRule::Weight, Some(weight)) => weight,
+        _ => 1,
+    };
+
+    match db::set_board_role(board.board_id, role.id, rule, weight) {
+        Ok(()) => {
+            let rule = match rule {
+                db::RoleRule::Weight => format!("weight {}", weight),
+                _ => rule.name().to_string(),
+            };
+
+            say_without_pings(
+                ctx,
+                format!("Board '{}' will {} <@&{}>", name, rule, role.id),
+            )
+            .await?;
+        }
+        Err(err) => {
+            ctx.say(format!("Failed to add role: {}", err)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the rule of a role
+#[poise::command(slash_command, guild_only, owners_only)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+    #[description = "Role"] role: serenity::Role,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    match db::remove_board_role(board.board_id, role.id) {
+        Ok(true) => {
+            say_without_pings(
+                ctx,
+                format!("Removed <@&{}> from board '{}'", role.id, name),
+            )
+            .await?;
+        }
+        Ok(false) => {
+            say_without_pings(
+                ctx,
+                format!("Board '{}' has no rule for <@&{}>", name, role.id),
+            )
+            .await?;
+        }
+        Err(err) => {
+            ctx.say(format!("Failed to remove role: {}", err)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// List the role rules of a board
+#[poise::command(slash_command, guild_only)]
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    let roles = db::get_board_roles(board.board_id)?;
+    if roles.is_empty() {
+        ctx.say(format!(
+            "Board '{}' counts reactions from every role",
+            board.name
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let lines = roles
+        .iter()
+        .map(|role| match role.rule {
+            db::RoleRule::Weight => format!("<@&{}> → weight {}", role.role_id, role.weight),
+            _ => format!("<@&{}> → {}", role.role_id, role.rule.name()),
+        })
+        .collect::<Vec<String>>();
+
+    say_without_pings(
+        ctx,
+        format!("**{}** roles\n{}", board.name, lines.join("\n")),
+    )
+    .await
+}
+
+// role mentions are only shown, nobody with the role is pinged
+async fn say_without_pings(ctx: Context<'_>, content: String) -> Result<(), Error> {
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
-use crate::{Context, Error, commands::autocomplete_board_names, db};
-use poise::serenity_prelude as serenity;
+use crate::{Context, Error, commands::autocomplete_board_names, db, render};
+use poise::{ChoiceParameter, serenity_prelude as serenity};
 
 #[poise::command(slash_command, guild_only, owners_only)]
+#[allow(clippy::too_many_arguments)]
 pub async fn editboard(
     ctx: Context<'_>,
     #[description = "Name of the board to edit"]
@@ -14,14 +15,86 @@ pub async fn editboard(
     #[min = 1]
     #[max = 50]
     min_reactions: Option<i64>,
+    #[description = "New removal threshold (0 to never remove)"]
+    #[min = 0]
+    #[max = 50]
+    remove_below: Option<i64>,
+    #[description = "What to do with board posts when the original message is deleted"]
+    on_delete: Option<db::DeletePolicy>,
+    #[description = "How reaction counts are combined"] count_mode: Option<db::CountMode>,
+    #[description = "Handling of self reactions"] self_star: Option<db::SelfStarPolicy>,
+    #[description = "Handling of bot reactions"] bot_reactions: Option<db::BotPolicy>,
+    #[description = "Post header, placeholders: {board} {count} {emoji} {channel} {author} {link}"]
+    header: Option<String>,
+    #[description = "Count tiers as count:emoji:#color separated by spaces (\"none\" to clear)"]
+    tiers: Option<String>,
+    #[description = "Upload small videos so they play inline"] reupload_videos: Option<bool>,
+    #[description = "Ignore messages older than this many days (0 for no limit)"]
+    #[min = 0]
+    #[max = 3650]
+    max_age_days: Option<i64>,
+    #[description = "Post messages through a webhook as their author"] webhook_mode: Option<bool>,
+    #[description = "Open a discussion thread on every post"] create_threads: Option<bool>,
+    #[description = "Tag forum posts with the tags of their reactions"] forum_tags: Option<bool>,
+    #[description = "Count reactions on board posts too"] count_board_reactions: Option<bool>,
+    #[description = "Posts per author in the author window (0 for no limit)"]
+    #[min = 0]
+    author_limit: Option<i64>,
+    #[description = "Length of the author window in hours"]
+    #[min = 1]
+    #[max = 720]
+    author_window_hours: Option<i64>,
+    #[description = "Minutes between posts by the same author (0 for no cooldown)"]
+    #[min = 0]
+    author_cooldown_minutes: Option<i64>,
+    #[description = "What to do with messages that qualify while their author is over the limits"]
+    throttle_policy: Option<db::ThrottlePolicy>,
 ) -> Result<(), Error> {
     let guild_id = ctx
         .guild_id()
         .ok_or("This command can only be used in a guild")?;
 
     // check if board exists
-    if db::get_board(guild_id.to_string(), name.clone()).is_err() {
-        ctx.say(format!("Board '{}' not found!", name)).await?;
+    let board = match db::get_board(guild_id.to_string(), name.clone()) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    // removal threshold must stay at or below the posting threshold
+    let new_min = min_reactions.unwrap_or(board.min_reactions as i64);
+    let new_remove_below = remove_below.or(board.remove_below.map(i64::from));
+    if new_remove_below.unwrap_or(0) > new_min {
+        ctx.say("The removal threshold can't be higher than the minimum number of reactions")
+            .await?;
+        return Ok(());
+    }
+
+    // "default" resets the header template, an empty header is saved as no header so the board
+    // follows the default template
+    let header = header.map(|header| match header.as_str() {
+        "default" => String::new(),
+        _ => header,
+    });
+
+    if let Some(Err(err)) = header
+        .as_deref()
+        .filter(|header| !header.is_empty())
+        .map(render::validate_header)
+    {
+        ctx.say(format!("Invalid header: {}", err)).await?;
+        return Ok(());
+    }
+
+    let tiers = tiers.map(|tiers| match tiers.as_str() {
+        "none" => String::new(),
+        _ => tiers,
+    });
+
+    if let Some(Err(err)) = tiers.as_deref().map(render::parse_tiers) {
+        ctx.say(format!("Invalid tiers: {}", err)).await?;
         return Ok(());
     }
 
@@ -37,21 +110,38 @@ pub async fn editboard(
         None
     };
 
-    match db::edit_board(
-        guild_id,
-        &name,
-        new_name.clone(),
-        parsed_reactions,
+    let edit = db::BoardEdit {
+        name: new_name.clone(),
+        reactions: parsed_reactions,
         min_reactions,
-        dest_channel.as_ref(),
-    ) {
+        dest_channel: dest_channel.as_ref().map(|channel| channel.id.to_string()),
+        remove_below,
+        on_delete,
+        count_mode,
+        self_star,
+        bot_reactions,
+        header: header.clone(),
+        tiers: tiers.clone(),
+        reupload_videos,
+        max_message_age: max_age_days.map(|days| days * 24 * 60 * 60),
+        webhook_mode,
+        create_threads,
+        forum_tags,
+        count_board_reactions,
+        author_limit,
+        author_window: author_window_hours.map(|hours| hours * 60 * 60),
+        author_cooldown: author_cooldown_minutes.map(|minutes| minutes * 60),
+        throttle_policy,
+    };
+
+    match db::edit_board(guild_id, &name, edit) {
         Ok(()) => {
             let mut changes = Vec::new();
             if let Some(new_name) = new_name {
                 changes.push(format!("name → {}", new_name));
             }
             if let Some(channel) = dest_channel {
-                changes.push(format!("destination → <#{}>", channel.id.to_string()));
+                changes.push(format!("destination → <#{}>", channel.id));
             }
             if reactions.is_some() {
                 changes.push("reactions updated".to_string());
@@ -59,6 +149,66 @@ pub async fn editboard(
             if let Some(min) = min_reactions {
                 changes.push(format!("min reactions → {}", min));
             }
+            if let Some(remove_below) = remove_below {
+                changes.push(format!("remove below → {}", remove_below));
+            }
+            if let Some(on_delete) = on_delete {
+                changes.push(format!("on delete → {}", on_delete.name()));
+            }
+            if let Some(count_mode) = count_mode {
+                changes.push(format!("count mode → {}", count_mode.name()));
+            }
+            if let Some(self_star) = self_star {
+                changes.push(format!("self stars → {}", self_star.name()));
+            }
+            if let Some(bot_reactions) = bot_reactions {
+                changes.push(format!("bot reactions → {}", bot_reactions.name()));
+            }
+            if let Some(header) = header {
+                match header.as_str() {
+                    "" => changes.push("header reset".to_string()),
+                    _ => changes.push(format!("header → `{}`", header)),
+                }
+            }
+            if let Some(tiers) = tiers {
+                match tiers.as_str() {
+                    "" => changes.push("tiers cleared".to_string()),
+                    _ => changes.push(format!("tiers → {}", tiers)),
+                }
+            }
+            if let Some(reupload_videos) = reupload_videos {
+                changes.push(format!("reupload videos → {}", reupload_videos));
+            }
+            if let Some(max_age_days) = max_age_days {
+                changes.push(format!("max message age → {} days", max_age_days));
+            }
+            if let Some(webhook_mode) = webhook_mode {
+                changes.push(format!("webhook mode → {}", webhook_mode));
+            }
+            if let Some(create_threads) = create_threads {
+                changes.push(format!("create threads → {}", create_threads));
+            }
+            if let Some(forum_tags) = forum_tags {
+                changes.push(format!("forum tags → {}", forum_tags));
+            }
+            if let Some(count_board_reactions) = count_board_reactions {
+                changes.push(format!("count board reactions → {}", count_board_reactions));
+            }
+            if let Some(author_limit) = author_limit {
+                changes.push(format!("author limit → {}", author_limit));
+            }
+            if let Some(author_window_hours) = author_window_hours {
+                changes.push(format!("author window → {} hours", author_window_hours));
+            }
+            if let Some(author_cooldown_minutes) = author_cooldown_minutes {
+                changes.push(format!(
+                    "author cooldown → {} minutes",
+                    author_cooldown_minutes
+                ));
+            }
+            if let Some(throttle_policy) = throttle_policy {
+                changes.push(format!("throttle policy → {}", throttle_policy.name()));
+            }
 
             let changes_str = if changes.is_empty() {
                 "No changes made".to_string()
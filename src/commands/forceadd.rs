@@ -0,0 +1,75 @@
+use crate::{Context, Error, commands::autocomplete_board_names, db};
+use poise::serenity_prelude as serenity;
+
+// post a message to a board regardless of its age and author limits, it still needs the board's
+// minimum reactions so the next recount doesn't take it off again
+#[poise::command(slash_command, guild_only, owners_only)]
+pub async fn forceadd(
+    ctx: Context<'_>,
+    #[description = "Name of the board"]
+    #[autocomplete = "autocomplete_board_names"]
+    name: String,
+    #[description = "Link to the message to post"] message: serenity::Message,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command can only be used in a guild")?;
+
+    let board = match db::get_board(guild_id, &name) {
+        Ok(board) => board,
+        Err(_) => {
+            ctx.say(format!("Board '{}' not found!", name)).await?;
+            return Ok(());
+        }
+    };
+
+    // only messages from this server can be posted to its boards
+    let message_guild = message
+        .channel_id
+        .to_channel(ctx)
+        .await?
+        .guild()
+        .map(|channel| channel.guild_id);
+    if message_guild != Some(guild_id) {
+        ctx.say("That message isn't from this server").await?;
+        return Ok(());
+    }
+
+    // counting the reactions can take a while on busy messages
+    ctx.defer().await?;
+
+    let serenity_ctx = ctx.serenity_context();
+    let _guard = ctx.data().locks.lock(message.id).await;
+
+    let message =
+        crate::fetch_message(serenity_ctx, guild_id, message.channel_id, message.id).await?;
+    let count = crate::count_reactions(serenity_ctx, ctx.data(), &message, &board).await?;
+
+    if count < board.min_reactions as usize {
+        ctx.say(format!(
+            "That message has {} of the {} reactions board '{}' needs",
+            count, board.min_reactions, name
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    match crate::create_board_post(serenity_ctx, &message, &board, count).await {
+        Ok(true) => {
+            ctx.say(format!("Message posted to board '{}'!", name))
+                .await?;
+        }
+        Ok(false) => {
+            ctx.say(format!(
+                "Message is already on board '{}' or can't be posted there",
+                name
+            ))
+            .await?;
+        }
+        Err(err) => {
+            ctx.say(format!("Failed to post message: {}", err)).await?;
+        }
+    }
+
+    Ok(())
+}
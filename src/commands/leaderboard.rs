@@ -33,7 +33,7 @@ pub async fn leaderboard(
             }
 
             // sort desc by reaction count
-            data.sort_by(|a, b| b.1.cmp(&a.1));
+            data.sort_by_key(|entry| std::cmp::Reverse(entry.1));
 
             // create pages of 10 results each
             let pages = create_leaderboard_pages(&data);
@@ -86,7 +86,7 @@ fn create_leaderboard_pages(data: &[(serenity::UserId, u64)]) -> Vec<String> {
         }
 
         // footer
-        let total_pages = (data.len() + ENTRIES_PER_PAGE - 1) / ENTRIES_PER_PAGE;
+        let total_pages = data.len().div_ceil(ENTRIES_PER_PAGE);
         page_content.push_str(&format!("\n*Page {} of {}*", page_num + 1, total_pages));
 
         pages.push(page_content);
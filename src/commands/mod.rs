@@ -1,14 +1,20 @@
 pub mod addboard;
+pub mod boardchannels;
+pub mod boardroles;
 pub mod deleteboard;
 pub mod editboard;
+pub mod forceadd;
 pub mod leaderboard;
 pub mod moststarred;
 pub mod random;
 pub mod showboard;
 
 pub use addboard::addboard;
+pub use boardchannels::boardchannels;
+pub use boardroles::boardroles;
 pub use deleteboard::deleteboard;
 pub use editboard::editboard;
+pub use forceadd::forceadd;
 pub use leaderboard::leaderboard;
 pub use moststarred::moststarred;
 pub use random::random;
@@ -45,7 +51,7 @@ pub fn parse_reactions(reactions: String) -> Vec<ReactionType> {
     }
 
     // remove duplicates
-    parsed_reactions.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    parsed_reactions.sort_by_key(|a| a.to_string());
     parsed_reactions.dedup_by(|a, b| a.to_string() == b.to_string());
     parsed_reactions
 }
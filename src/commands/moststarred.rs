@@ -1,7 +1,7 @@
 use crate::{
     Context, Error,
     commands::{autocomplete_board_names, create_reply},
-    db,
+    db, posts,
 };
 use poise::serenity_prelude::*;
 
@@ -30,10 +30,17 @@ pub async fn moststarred(
                 .iter()
                 .max_by(|a, b| a.reaction_count.cmp(&b.reaction_count))
             {
-                let board = db::get_board_by_id(&max.board_id).unwrap();
+                let board = match db::get_board_by_id(max.board_id) {
+                    Ok(board) => board,
+                    Err(err) => {
+                        ctx.say(format!("Board not found: {}", err)).await?;
+                        return Ok(());
+                    }
+                };
 
-                let message_id = MessageId::new(max.dest_id.parse().unwrap());
-                let channel_id = ChannelId::new(board.dest_channel.parse().unwrap());
+                // forum posts live in their own thread rather than in the board channel
+                let message_id = MessageId::new(max.dest_id.parse()?);
+                let channel_id = posts::post_channel(&board, max)?;
 
                 match ctx.http().get_message(channel_id, message_id).await {
                     Ok(message) => match create_reply(ctx, message).await {
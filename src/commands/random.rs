@@ -1,7 +1,7 @@
 use crate::{
     Context, Error,
     commands::{autocomplete_board_names, create_reply},
-    db,
+    db, posts,
 };
 use poise::serenity_prelude::*;
 use rand::Rng;
@@ -26,14 +26,24 @@ pub async fn random(
     };
 
     match message_data {
+        Ok(data) if data.is_empty() => {
+            ctx.say("No messages found").await?;
+        }
         Ok(data) => {
             let num = rand::thread_rng().gen_range(0..data.len());
             let selected = data.get(num).unwrap();
 
-            let board = db::get_board_by_id(&selected.board_id).unwrap();
+            let board = match db::get_board_by_id(selected.board_id) {
+                Ok(board) => board,
+                Err(err) => {
+                    ctx.say(format!("Board not found: {}", err)).await?;
+                    return Ok(());
+                }
+            };
 
-            let message_id = MessageId::new(selected.dest_id.parse().unwrap());
-            let channel_id = ChannelId::new(board.dest_channel.parse().unwrap());
+            // forum posts live in their own thread rather than in the board channel
+            let message_id = MessageId::new(selected.dest_id.parse()?);
+            let channel_id = posts::post_channel(&board, selected)?;
 
             match ctx.http().get_message(channel_id, message_id).await {
                 Ok(message) => match create_reply(ctx, message).await {
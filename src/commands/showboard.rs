@@ -1,4 +1,5 @@
-use crate::{Context, Error, commands::autocomplete_board_names, db};
+use crate::{Context, Error, commands::autocomplete_board_names, db, render};
+use poise::ChoiceParameter;
 
 #[poise::command(slash_command, guild_only)]
 pub async fn showboard(
@@ -16,19 +17,57 @@ pub async fn showboard(
             // specific board
             match db::get_board(guild_id, &board_name) {
                 Ok(board) => {
-                    let reactions = db::from_csv(board.reactions);
+                    let reactions = db::from_csv(board.reactions.clone());
                     let reaction_str = reactions
                         .iter()
                         .map(|r| r.to_string())
                         .collect::<Vec<_>>()
                         .join(" ");
 
+                    let max_message_age = match board.max_message_age {
+                        Some(age) if age > 0 => format!("{} days", age / (24 * 60 * 60)),
+                        _ => "none".to_string(),
+                    };
+
+                    let author_limit = match board.author_limit {
+                        Some(limit) if limit > 0 => {
+                            format!("{} per {} hours", limit, board.author_window / (60 * 60))
+                        }
+                        _ => "none".to_string(),
+                    };
+
+                    let author_cooldown = match board.author_cooldown {
+                        Some(cooldown) if cooldown > 0 => format!("{} minutes", cooldown / 60),
+                        _ => "none".to_string(),
+                    };
+
+                    let remove_below = match board.remove_below {
+                        Some(remove_below) if remove_below > 0 => remove_below.to_string(),
+                        _ => "never".to_string(),
+                    };
+
                     ctx.say(format!(
-                        "**Board: {}**\n**Destination:** <#{}>\n**Reactions:** {}\n**Min Reactions:** {}",
+                        "**Board: {}**\n**Destination:** <#{}>\n**Reactions:** {}\n**Min Reactions:** {}\n**Remove Below:** {}\n**On Delete:** {}\n**Count Mode:** {}\n**Self Stars:** {}\n**Bot Reactions:** {}\n**Header:** `{}`\n**Tiers:** {}\n**Reupload Videos:** {}\n**Max Message Age:** {}\n**Webhook Mode:** {}\n**Create Threads:** {}\n**Forum Tags:** {}\n**Count Board Reactions:** {}\n**Author Limit:** {}\n**Author Cooldown:** {}\n**Throttle Policy:** {}",
                         board.name,
                         board.dest_channel,
                         reaction_str,
-                        board.min_reactions
+                        board.min_reactions,
+                        remove_below,
+                        board.on_delete.name(),
+                        board.count_mode.name(),
+                        board.self_star.name(),
+                        board.bot_reactions.name(),
+                        board.header.as_deref().unwrap_or(render::default_header(&board)),
+                        board.tiers.as_deref().filter(|tiers| !tiers.is_empty()).unwrap_or("none"),
+                        board.reupload_videos,
+                        max_message_age,
+                        board.webhook_mode,
+                        board.create_threads,
+                        board.forum_tags,
+                        board.count_board_reactions,
+                        author_limit,
+                        author_cooldown,
+                        board.throttle_policy.name()
                     )).await?;
                 }
                 Err(_) => {
@@ -46,7 +85,7 @@ pub async fn showboard(
                     } else {
                         let mut response = "**Server Boards:**\n\n".to_string();
                         for board in boards {
-                            let reactions = db::from_csv(board.reactions);
+                            let reactions = db::from_csv(board.reactions.clone());
                             let reaction_str = reactions
                                 .iter()
                                 .map(|r| r.to_string())
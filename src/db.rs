@@ -1,5 +1,5 @@
-use poise::serenity_prelude::*;
-use rusqlite::{Connection, Result};
+use poise::{ChoiceParameter, serenity_prelude::*};
+use rusqlite::{Connection, OptionalExtension, Result, params};
 
 const DB_NAME: &str = "settings.db";
 
@@ -7,34 +7,302 @@ fn get_connection() -> Result<Connection> {
     Connection::open(DB_NAME)
 }
 
+#[derive(Default)]
 pub struct Board {
+    pub board_id: i64,
     pub name: String,
     pub reactions: String,
     pub min_reactions: i32,
     pub dest_channel: String,
+    pub remove_below: Option<i32>,
+    pub on_delete: DeletePolicy,
+    pub count_mode: CountMode,
+    pub self_star: SelfStarPolicy,
+    pub bot_reactions: BotPolicy,
+    pub header: Option<String>,
+    pub tiers: Option<String>,
+    pub reupload_videos: bool,
+    pub max_message_age: Option<i64>,
+    pub webhook_mode: bool,
+    pub webhook_url: Option<String>,
+    pub create_threads: bool,
+    pub forum_tags: bool,
+    pub count_board_reactions: bool,
+    pub author_limit: Option<i64>,
+    pub author_window: i64,
+    pub author_cooldown: Option<i64>,
+    pub throttle_policy: ThrottlePolicy,
+}
+
+// what happens to a board post when its source message is deleted
+#[derive(Clone, Copy, Default, PartialEq, ChoiceParameter)]
+pub enum DeletePolicy {
+    #[default]
+    #[name = "delete"]
+    Delete,
+    #[name = "tombstone"]
+    Tombstone,
+    #[name = "keep"]
+    Keep,
+}
+
+// how the reactions of a board's emojis are combined into one count
+#[derive(Clone, Copy, Default, PartialEq, ChoiceParameter)]
+pub enum CountMode {
+    // highest count of any single emoji
+    #[default]
+    #[name = "per_emoji_max"]
+    PerEmojiMax,
+    // counts of all emojis added together
+    #[name = "sum"]
+    Sum,
+    // users that reacted with any of the emojis
+    #[name = "unique_users"]
+    UniqueUsers,
+}
+
+// how reactions from the author of the message are handled
+#[derive(Clone, Copy, Default, PartialEq, ChoiceParameter)]
+pub enum SelfStarPolicy {
+    #[name = "count"]
+    Count,
+    #[default]
+    #[name = "ignore"]
+    Ignore,
+    // remove the reaction from the message
+    #[name = "remove"]
+    Remove,
+}
+
+// how reactions from bot accounts are handled
+#[derive(Clone, Copy, Default, PartialEq, ChoiceParameter)]
+pub enum BotPolicy {
+    #[name = "count"]
+    Count,
+    #[default]
+    #[name = "ignore"]
+    Ignore,
+}
+
+// what happens to a message that reached a board while its author is over the board's limits
+#[derive(Clone, Copy, Default, PartialEq, ChoiceParameter)]
+pub enum ThrottlePolicy {
+    // post the message once the author is within the limits again
+    #[default]
+    #[name = "queue"]
+    Queue,
+    // leave the message off the board for good
+    #[name = "drop"]
+    Drop,
+}
+
+// whether a board takes messages from a channel, or from every channel of a category
+#[derive(Clone, Copy, PartialEq, ChoiceParameter)]
+pub enum ChannelRule {
+    #[name = "include"]
+    Include,
+    #[name = "exclude"]
+    Exclude,
+}
+
+// how reactions from members with a role are counted on a board
+#[derive(Clone, Copy, PartialEq, ChoiceParameter)]
+pub enum RoleRule {
+    // reactions from members with the role don't count
+    #[name = "exclude"]
+    Exclude,
+    // only reactions from members with one of the required roles count
+    #[name = "require"]
+    Require,
+    // reactions from members with the role count as the weight of the role
+    #[name = "weight"]
+    Weight,
+}
+
+pub struct BoardRole {
+    pub role_id: String,
+    pub rule: RoleRule,
+    pub weight: i64,
+}
+
+// board settings to change, fields left as None keep their current value
+#[derive(Default)]
+pub struct BoardEdit {
+    pub name: Option<String>,
+    pub reactions: Option<Vec<ReactionType>>,
+    pub min_reactions: Option<i64>,
+    pub dest_channel: Option<String>,
+    pub remove_below: Option<i64>,
+    pub on_delete: Option<DeletePolicy>,
+    pub count_mode: Option<CountMode>,
+    pub self_star: Option<SelfStarPolicy>,
+    pub bot_reactions: Option<BotPolicy>,
+    // an empty header clears the template so the board follows the default one
+    pub header: Option<String>,
+    pub tiers: Option<String>,
+    pub reupload_videos: Option<bool>,
+    pub max_message_age: Option<i64>,
+    pub webhook_mode: Option<bool>,
+    pub create_threads: Option<bool>,
+    pub forum_tags: Option<bool>,
+    pub count_board_reactions: Option<bool>,
+    pub author_limit: Option<i64>,
+    pub author_window: Option<i64>,
+    pub author_cooldown: Option<i64>,
+    pub throttle_policy: Option<ThrottlePolicy>,
+}
+
+const BOARD_COLUMNS: &str = "boards.board_id, boards.name, boards.reactions, boards.min_reactions, \
+    boards.dest_channel, boards.remove_below, boards.on_delete, boards.count_mode, \
+    boards.self_star, boards.bot_reactions, boards.header, \
+    boards.tiers, boards.reupload_videos, boards.max_message_age, boards.webhook_mode, \
+    boards.webhook_url, boards.create_threads, boards.forum_tags, \
+    boards.count_board_reactions, boards.author_limit, boards.author_window, \
+    boards.author_cooldown, boards.throttle_policy";
+
+fn board_from_row(row: &rusqlite::Row) -> Result<Board> {
+    Ok(Board {
+        board_id: row.get("board_id")?,
+        name: row.get("name")?,
+        reactions: row.get("reactions")?,
+        min_reactions: row.get("min_reactions")?,
+        dest_channel: row.get("dest_channel")?,
+        remove_below: row.get("remove_below")?,
+        on_delete: DeletePolicy::from_name(&row.get::<&str, String>("on_delete")?)
+            .unwrap_or_default(),
+        count_mode: CountMode::from_name(&row.get::<&str, String>("count_mode")?)
+            .unwrap_or_default(),
+        self_star: SelfStarPolicy::from_name(&row.get::<&str, String>("self_star")?)
+            .unwrap_or_default(),
+        bot_reactions: BotPolicy::from_name(&row.get::<&str, String>("bot_reactions")?)
+            .unwrap_or_default(),
+        header: row.get("header")?,
+        tiers: row.get("tiers")?,
+        reupload_videos: row.get("reupload_videos")?,
+        max_message_age: row.get("max_message_age")?,
+        webhook_mode: row.get("webhook_mode")?,
+        webhook_url: row.get("webhook_url")?,
+        create_threads: row.get("create_threads")?,
+        forum_tags: row.get("forum_tags")?,
+        count_board_reactions: row.get("count_board_reactions")?,
+        author_limit: row.get("author_limit")?,
+        author_window: row.get("author_window")?,
+        author_cooldown: row.get("author_cooldown")?,
+        throttle_policy: ThrottlePolicy::from_name(&row.get::<&str, String>("throttle_policy")?)
+            .unwrap_or_default(),
+    })
 }
 
 pub struct Message {
     pub user_id: String,
     pub source_id: String,
+    pub source_channel: Option<String>,
     pub dest_id: String,
     pub board_id: i64,
     pub reaction_count: i64,
+    pub sent_webhook_url: Option<String>,
+    pub thread_id: Option<String>,
 }
 
+const MESSAGE_COLUMNS: &str = "messages.user_id, messages.source_id, messages.source_channel, \
+    messages.dest_id, messages.board_id, messages.reaction_count, \
+    messages.sent_webhook_url, messages.thread_id";
+
+fn message_from_row(row: &rusqlite::Row) -> Result<Message> {
+    Ok(Message {
+        user_id: row.get("user_id")?,
+        source_id: row.get("source_id")?,
+        source_channel: row.get("source_channel")?,
+        dest_id: row.get("dest_id")?,
+        board_id: row.get("board_id")?,
+        reaction_count: row.get("reaction_count")?,
+        sent_webhook_url: row.get("sent_webhook_url")?,
+        thread_id: row.get("thread_id")?,
+    })
+}
+
+// columns added after the initial schema, added to new databases and ones created by older versions
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("boards", "remove_below", "INT"),
+    ("boards", "on_delete", "TEXT NOT NULL DEFAULT 'delete'"),
+    (
+        "boards",
+        "count_mode",
+        "TEXT NOT NULL DEFAULT 'per_emoji_max'",
+    ),
+    ("boards", "self_star", "TEXT NOT NULL DEFAULT 'ignore'"),
+    ("boards", "bot_reactions", "TEXT NOT NULL DEFAULT 'ignore'"),
+    ("boards", "header", "TEXT"),
+    ("boards", "tiers", "TEXT"),
+    ("boards", "reupload_videos", "INTEGER NOT NULL DEFAULT 0"),
+    ("boards", "max_message_age", "INT"),
+    ("boards", "webhook_mode", "INTEGER NOT NULL DEFAULT 0"),
+    ("boards", "webhook_url", "TEXT"),
+    ("messages", "posted", "INTEGER NOT NULL DEFAULT 1"),
+    ("messages", "sent_webhook_url", "TEXT"),
+    ("messages", "thread_id", "TEXT"),
+    ("boards", "create_threads", "INTEGER NOT NULL DEFAULT 0"),
+    ("boards", "forum_tags", "INTEGER NOT NULL DEFAULT 0"),
+    (
+        "boards",
+        "count_board_reactions",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("messages", "source_channel", "TEXT"),
+    ("boards", "author_limit", "INT"),
+    ("boards", "author_window", "INT NOT NULL DEFAULT 86400"),
+    ("boards", "author_cooldown", "INT"),
+    ("boards", "throttle_policy", "TEXT NOT NULL DEFAULT 'queue'"),
+    ("messages", "posted_at", "INT"),
+    ("messages", "throttled", "INTEGER NOT NULL DEFAULT 0"),
+];
+
 pub fn create_db() -> Result<()> {
-    if !std::path::Path::new(DB_NAME).exists() {
-        let conn = get_connection()?;
+    let exists = std::path::Path::new(DB_NAME).exists();
+    let conn = get_connection()?;
 
+    if !exists {
         // min_reactions is the threshold for a message to be posted to the board
         // reactions holds csv of reaction IDs
         // dest_channel holds the channel ID of the channel that the message will be posted to
+        // remove_below is the threshold under which a posted message is removed from the board,
+        // NULL if messages are never removed
+        // on_delete holds the DeletePolicy name applied when a source message is deleted
+        // count_mode holds the CountMode name used to combine the counts of the reactions
+        // self_star holds the SelfStarPolicy name applied to reactions from the message author
+        // bot_reactions holds the BotPolicy name applied to reactions from bot accounts
+        // header holds the template of the board post header, NULL for the default template
+        // tiers holds space-separated count:emoji:#color tiers of the board post display
+        // reupload_videos is 1 if small videos are uploaded to the board to play inline
+        // max_message_age is the age in seconds past which messages aren't posted anymore,
+        // NULL or 0 if messages of any age are posted
+        // webhook_mode is 1 if messages are posted through a webhook as their author
+        // webhook_url holds the URL of the webhook created in dest_channel, NULL until one is needed
+        // create_threads is 1 if a discussion thread is opened on every board message
+        // forum_tags is 1 if posts in a forum dest_channel get the tags matching their reactions
+        // count_board_reactions is 1 if reactions on the board message count toward its source
+        // author_limit is the number of messages by one author posted per author_window seconds,
+        // NULL or 0 for no limit
+        // author_cooldown is the time in seconds between two posts by the same author,
+        // NULL or 0 for no cooldown
+        // throttle_policy holds the ThrottlePolicy name applied to messages over those limits
         //
         // user_id holds the user ID of the user that posted the message
         // source_id holds the message ID of the message that passed the reaction threshold
+        // source_channel holds the channel ID of the source message, NULL for older messages
         // dest_id holds the message ID of the message that was posted to the board
         // board_id holds the ID of the board that the message reached the threshold for
         // reaction_count holds the number of reactions that is displayed on the destination message
+        // posted is 0 if the message was removed from the board after falling below remove_below,
+        // a posted message without dest_id is claimed by a task that is still posting it
+        // sent_webhook_url holds the URL of the webhook the board message was sent through, NULL if
+        // the bot sent it
+        // posted_at is the unix time the message was last posted to the board, NULL for older
+        // messages
+        // throttled is 1 if the message was dropped by the board's author limits, it is only
+        // posted by /forceadd after that
+        // thread_id holds the channel ID of the discussion thread of the board message, for posts
+        // in forum channels the thread is the post itself and thread_id is the same as dest_id
         conn.execute_batch(
             "CREATE TABLE boards (
                 board_id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -44,7 +312,7 @@ pub fn create_db() -> Result<()> {
                 reactions TEXT,
                 min_reactions INT,
                 dest_channel TEXT
-            )
+            );
 
             CREATE TABLE messages (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -56,10 +324,95 @@ pub fn create_db() -> Result<()> {
                 reaction_count INTEGER,
 
                 FOREIGN KEY(board_id) REFERENCES boards(board_id) ON DELETE CASCADE
-            )",
+            );",
         )?;
     }
 
+    // each source message has at most one post per board, older versions could create
+    // duplicates for messages that were posted to multiple boards
+    if !index_exists(&conn, "messages_board_source")? {
+        conn.execute_batch(
+            "DELETE FROM messages
+                WHERE id NOT IN (SELECT MIN(id) FROM messages GROUP BY board_id, source_id);
+
+            CREATE UNIQUE INDEX messages_board_source ON messages(board_id, source_id);",
+        )?;
+    }
+
+    for (table, column, definition) in ADDED_COLUMNS {
+        add_missing_column(&conn, table, column, definition)?;
+    }
+
+    // board posts are looked up by their board message on reactions to them
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS messages_dest ON messages(dest_id);")?;
+
+    // channel_id holds the ID of a channel or category
+    // rule holds the ChannelRule name applied to messages from that channel, boards with any
+    // include rule only take messages from included channels
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS board_channels (
+            board_id INTEGER,
+            channel_id TEXT,
+            rule TEXT,
+
+            PRIMARY KEY(board_id, channel_id),
+            FOREIGN KEY(board_id) REFERENCES boards(board_id) ON DELETE CASCADE
+        );",
+    )?;
+
+    // role_id holds the ID of a role
+    // rule holds the RoleRule name applied to reactions from members with that role
+    // weight holds how many reactions a reaction counts as for weight rules, members with
+    // several weighted roles count as the highest weight
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS board_roles (
+            board_id INTEGER,
+            role_id TEXT,
+            rule TEXT,
+            weight INTEGER NOT NULL DEFAULT 1,
+
+            PRIMARY KEY(board_id, role_id),
+            FOREIGN KEY(board_id) REFERENCES boards(board_id) ON DELETE CASCADE
+        );",
+    )?;
+
+    // release claims that never got a board post because the bot stopped while posting
+    conn.execute(
+        "UPDATE messages SET posted = 0 WHERE posted = 1 AND dest_id IS NULL",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn index_exists(conn: &Connection, index: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?",
+        [index],
+        |row| row.get::<usize, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+fn add_missing_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<usize, String>(0))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))?;
+    }
+
     Ok(())
 }
 
@@ -84,19 +437,21 @@ pub fn add_board(
     reactions: Vec<ReactionType>,
     min_reactions: Option<i64>,
     dest_channel: impl ToString,
+    remove_below: Option<i64>,
 ) -> Result<()> {
     let conn = get_connection()?;
 
     conn.execute(
         "INSERT INTO boards
-            (guild_id, name, reactions, min_reactions, dest_channel)
-            VALUES (?, ?, ?, ?, ?)",
+            (guild_id, name, reactions, min_reactions, dest_channel, remove_below)
+            VALUES (?, ?, ?, ?, ?, ?)",
         (
             guild_id.to_string(),
             name.as_ref(),
             to_csv(reactions),
             min_reactions.unwrap_or(5),
             dest_channel.to_string(),
+            remove_below,
         ),
     )?;
 
@@ -118,10 +473,7 @@ pub fn delete_board(guild_id: impl ToString, board_name: impl AsRef<str>) -> Res
 pub fn edit_board(
     guild_id: impl ToString,
     board_name: impl AsRef<str>,
-    new_name: Option<String>,
-    reactions: Option<Vec<ReactionType>>,
-    min_reactions: Option<i64>,
-    dest_channel: Option<impl ToString>,
+    edit: BoardEdit,
 ) -> Result<()> {
     let conn = get_connection()?;
 
@@ -130,16 +482,54 @@ pub fn edit_board(
             SET name = COALESCE(?, name),
                 reactions = COALESCE(?, reactions),
                 min_reactions = COALESCE(?, min_reactions),
-                dest_channel = COALESCE(?, dest_channel)
+                dest_channel = COALESCE(?, dest_channel),
+                remove_below = COALESCE(?, remove_below),
+                on_delete = COALESCE(?, on_delete),
+                count_mode = COALESCE(?, count_mode),
+                self_star = COALESCE(?, self_star),
+                bot_reactions = COALESCE(?, bot_reactions),
+                header = NULLIF(COALESCE(?, header), ''),
+                tiers = COALESCE(?, tiers),
+                reupload_videos = COALESCE(?, reupload_videos),
+                max_message_age = COALESCE(?, max_message_age),
+                webhook_mode = COALESCE(?, webhook_mode),
+                create_threads = COALESCE(?, create_threads),
+                forum_tags = COALESCE(?, forum_tags),
+                count_board_reactions = COALESCE(?, count_board_reactions),
+                author_limit = COALESCE(?, author_limit),
+                author_window = COALESCE(?, author_window),
+                author_cooldown = COALESCE(?, author_cooldown),
+                throttle_policy = COALESCE(?, throttle_policy),
+                webhook_url = CASE WHEN COALESCE(?, dest_channel) = dest_channel
+                    THEN webhook_url END
             WHERE guild_id = ? AND name = ?",
-        (
-            new_name,
-            reactions.map(|r| to_csv(r)),
-            min_reactions,
-            dest_channel.map(|c| c.to_string()),
+        params![
+            edit.name,
+            edit.reactions.map(to_csv),
+            edit.min_reactions,
+            edit.dest_channel.clone(),
+            edit.remove_below,
+            edit.on_delete.map(|policy| policy.name()),
+            edit.count_mode.map(|mode| mode.name()),
+            edit.self_star.map(|policy| policy.name()),
+            edit.bot_reactions.map(|policy| policy.name()),
+            edit.header,
+            edit.tiers,
+            edit.reupload_videos,
+            edit.max_message_age,
+            edit.webhook_mode,
+            edit.create_threads,
+            edit.forum_tags,
+            edit.count_board_reactions,
+            edit.author_limit,
+            edit.author_window,
+            edit.author_cooldown,
+            edit.throttle_policy.map(|policy| policy.name()),
+            // the webhook of the old destination channel can't post to the new one
+            edit.dest_channel,
             guild_id.to_string(),
             board_name.as_ref(),
-        ),
+        ],
     )?;
 
     Ok(())
@@ -154,92 +544,260 @@ pub fn get_board_names(guild_id: impl ToString) -> Result<Vec<String>> {
             WHERE guild_id = ?",
     )?;
 
-    stmt.query_map([guild_id.to_string()], |row| Ok(row.get(0)?))?
+    stmt.query_map([guild_id.to_string()], |row| row.get(0))?
         .collect::<Result<Vec<String>>>()
 }
 
-// get board name, min_reactions and dest_channel given the boards contain passed ReactionType
-pub fn find_min_reactions(
-    guild_id: String,
-    reaction: ReactionType,
-) -> Result<Vec<(String, usize, String)>> {
+// get all boards of a guild that contain passed ReactionType
+pub fn find_reaction_boards(guild_id: String, reaction: ReactionType) -> Result<Vec<Board>> {
     let conn = get_connection()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT name, min_reactions, dest_channel
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
             FROM boards
             WHERE guild_id = ? AND reactions LIKE ?",
-    )?;
+        BOARD_COLUMNS
+    ))?;
 
-    stmt.query_map([guild_id, format!("%{}%", reaction)], |row| {
-        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    stmt.query_map([guild_id, format!("%{}%", reaction)], board_from_row)?
+        .collect::<Result<Vec<Board>>>()
+}
+
+// get the board post of a source message, None if it was removed from the board
+pub fn get_board_post(board_id: i64, source_id: impl ToString) -> Result<Option<Message>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+            FROM messages
+            WHERE board_id = ? AND source_id = ?",
+        MESSAGE_COLUMNS
+    ))?;
+
+    stmt.query_row(params![board_id, source_id.to_string()], |row| {
+        match row.get::<&str, Option<String>>("dest_id")? {
+            Some(_) => message_from_row(row).map(Some),
+            None => Ok(None),
+        }
+    })
+}
+
+// get every live board post of a source message along with its board
+pub fn get_source_posts(source_id: impl ToString) -> Result<Vec<(Board, Message)>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, {}
+            FROM messages
+            JOIN boards ON messages.board_id = boards.board_id
+            WHERE messages.source_id = ? AND posted = 1 AND dest_id IS NOT NULL",
+        BOARD_COLUMNS, MESSAGE_COLUMNS
+    ))?;
+
+    stmt.query_map([source_id.to_string()], |row| {
+        Ok((board_from_row(row)?, message_from_row(row)?))
     })?
-    .collect::<Result<Vec<(String, usize, String)>>>()
+    .collect::<Result<Vec<(Board, Message)>>>()
 }
 
-pub fn get_message_dest(guild_id: String, source_id: String) -> Result<String> {
+// whether any board of a guild counts the reactions on its board posts
+pub fn counts_board_reactions(guild_id: impl ToString) -> Result<bool> {
     let conn = get_connection()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT messages.dest_id
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM boards WHERE guild_id = ? AND count_board_reactions = 1)",
+        [guild_id.to_string()],
+        |row| row.get(0),
+    )
+}
+
+// get the live board post with a board message ID along with its board
+pub fn get_dest_post(dest_id: impl ToString) -> Result<Option<(Board, Message)>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, {}
             FROM messages
             JOIN boards ON messages.board_id = boards.board_id
-            WHERE boards.guild_id = ? AND messages.source_id = ?",
-    )?;
+            WHERE messages.dest_id = ? AND posted = 1",
+        BOARD_COLUMNS, MESSAGE_COLUMNS
+    ))?;
+
+    stmt.query_row([dest_id.to_string()], |row| {
+        Ok((board_from_row(row)?, message_from_row(row)?))
+    })
+    .optional()
+}
 
-    let message_id: String = stmt.query_row([guild_id, source_id], |row| row.get(0))?;
+// claim the board post of a message before posting it, returns false if the message is already
+// posted or another task is posting it, so only one board post is ever created per board
+pub fn claim_message(
+    board_id: i64,
+    user_id: impl ToString,
+    source_id: impl ToString,
+    source_channel: impl ToString,
+    reaction_count: i64,
+) -> Result<bool> {
+    let conn = get_connection()?;
 
-    Ok(message_id)
+    let claimed = conn.execute(
+        "INSERT INTO messages
+            (board_id, user_id, source_id, source_channel, dest_id, reaction_count, posted)
+            VALUES (?, ?, ?, ?, NULL, ?, 1)
+            ON CONFLICT(board_id, source_id) DO UPDATE
+                SET reaction_count = excluded.reaction_count,
+                    source_channel = excluded.source_channel,
+                    posted = 1,
+                    throttled = 0
+                WHERE posted = 0",
+        params![
+            board_id,
+            user_id.to_string(),
+            source_id.to_string(),
+            source_channel.to_string(),
+            reaction_count,
+        ],
+    )?;
+
+    Ok(claimed > 0)
 }
 
-// add a message to the messages table
-pub fn add_message(
-    guild_id: impl ToString,
-    board_name: impl ToString,
+// mark a message as dropped by the board's author limits, so it is never posted by reactions
+pub fn throttle_message(
+    board_id: i64,
     user_id: impl ToString,
     source_id: impl ToString,
-    dest_id: impl ToString,
+    source_channel: impl ToString,
     reaction_count: i64,
 ) -> Result<()> {
     let conn = get_connection()?;
 
     conn.execute(
         "INSERT INTO messages
-            (board_id, user_id, source_id, dest_id, reaction_count)
-            VALUES ((SELECT board_id FROM boards WHERE guild_id = ? AND name = ?), ?, ?, ?, ?)",
-        (
-            guild_id.to_string(),
-            board_name.to_string(),
+            (board_id, user_id, source_id, source_channel, dest_id, reaction_count, posted,
+                throttled)
+            VALUES (?, ?, ?, ?, NULL, ?, 0, 1)
+            ON CONFLICT(board_id, source_id) DO UPDATE
+                SET reaction_count = excluded.reaction_count,
+                    throttled = 1
+                WHERE posted = 0",
+        params![
+            board_id,
             user_id.to_string(),
             source_id.to_string(),
-            dest_id.to_string(),
+            source_channel.to_string(),
             reaction_count,
-        ),
+        ],
     )?;
 
     Ok(())
 }
 
-pub fn get_guild_messages(guild_id: impl ToString) -> Result<Vec<Message>> {
+// whether a message was dropped by the board's author limits
+pub fn is_message_throttled(board_id: i64, source_id: impl ToString) -> Result<bool> {
+    let conn = get_connection()?;
+
+    conn.query_row(
+        "SELECT EXISTS(
+            SELECT 1 FROM messages WHERE board_id = ? AND source_id = ? AND throttled = 1
+        )",
+        params![board_id, source_id.to_string()],
+        |row| row.get(0),
+    )
+}
+
+// save the board message ID of a claimed message once it was posted
+pub fn set_message_dest(
+    board_id: i64,
+    source_id: impl ToString,
+    dest_id: impl ToString,
+    sent_webhook_url: Option<String>,
+) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE messages
+            SET dest_id = ?, sent_webhook_url = ?, posted_at = CAST(strftime('%s', 'now') AS INTEGER)
+            WHERE board_id = ? AND source_id = ?",
+        params![
+            dest_id.to_string(),
+            sent_webhook_url,
+            board_id,
+            source_id.to_string()
+        ],
+    )?;
+
+    Ok(())
+}
+
+// save the discussion thread of a board message
+pub fn set_message_thread(
+    board_id: i64,
+    source_id: impl ToString,
+    thread_id: impl ToString,
+) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE messages
+            SET thread_id = ?
+            WHERE board_id = ? AND source_id = ?",
+        params![thread_id.to_string(), board_id, source_id.to_string()],
+    )?;
+
+    Ok(())
+}
+
+// get the times messages by a user were posted to a board since a unix time, oldest first,
+// messages removed from the board since still count
+pub fn get_author_post_times(
+    board_id: i64,
+    user_id: impl ToString,
+    since: i64,
+) -> Result<Vec<i64>> {
     let conn = get_connection()?;
 
     let mut stmt = conn.prepare(
-        "SELECT user_id, source_id, dest_id, messages.board_id, reaction_count
+        "SELECT posted_at
             FROM messages
-            JOIN boards ON messages.board_id = boards.board_id
-            WHERE boards.guild_id = ?",
+            WHERE board_id = ? AND user_id = ? AND posted_at >= ?
+            ORDER BY posted_at",
     )?;
 
+    stmt.query_map(params![board_id, user_id.to_string(), since], |row| {
+        row.get(0)
+    })?
+    .collect::<Result<Vec<i64>>>()
+}
+
+// mark a message as removed from the board, keeping its reaction count,
+// also releases a claim if posting the message failed
+pub fn unpost_message(board_id: i64, source_id: impl ToString, reaction_count: i64) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE messages
+            SET dest_id = NULL, thread_id = NULL, reaction_count = ?, posted = 0
+            WHERE board_id = ? AND source_id = ?",
+        params![reaction_count, board_id, source_id.to_string()],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_guild_messages(guild_id: impl ToString) -> Result<Vec<Message>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+            FROM messages
+            JOIN boards ON messages.board_id = boards.board_id
+            WHERE boards.guild_id = ? AND posted = 1 AND dest_id IS NOT NULL",
+        MESSAGE_COLUMNS
+    ))?;
+
     Ok(stmt
-        .query_map([guild_id.to_string()], |row| {
-            Ok(Message {
-                user_id: row.get(0)?,
-                source_id: row.get(1)?,
-                dest_id: row.get(2)?,
-                board_id: row.get::<usize, i64>(3)?,
-                reaction_count: row.get(4)?,
-            })
-        })?
+        .query_map([guild_id.to_string()], message_from_row)?
         .filter_map(|f| f.ok())
         .collect::<Vec<_>>())
 }
@@ -256,30 +814,22 @@ pub fn get_board_messages(
         |row| row.get(0),
     )?;
 
-    let mut stmt = conn.prepare(
-        "SELECT user_id, source_id, dest_id, reaction_count
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
             FROM messages
-            WHERE board_id = ?",
-    )?;
+            WHERE board_id = ? AND posted = 1 AND dest_id IS NOT NULL",
+        MESSAGE_COLUMNS
+    ))?;
 
     Ok(stmt
-        .query_map([board_id], |row| {
-            Ok(Message {
-                user_id: row.get(0)?,
-                source_id: row.get(1)?,
-                dest_id: row.get(2)?,
-                board_id: board_id,
-                reaction_count: row.get(3)?,
-            })
-        })?
+        .query_map([board_id], message_from_row)?
         .filter_map(|f| f.ok())
         .collect::<Vec<Message>>())
 }
 
 // update reaction count of a message
 pub fn update_message_reaction_count(
-    guild_id: impl ToString,
-    board_name: impl ToString,
+    board_id: i64,
     source_id: impl ToString,
     reaction_count: i64,
 ) -> Result<()> {
@@ -288,14 +838,8 @@ pub fn update_message_reaction_count(
     conn.execute(
         "UPDATE messages
             SET reaction_count = ?
-            WHERE board_id = (SELECT board_id FROM boards WHERE guild_id = ? AND name = ?)
-                AND source_id = ?",
-        (
-            reaction_count,
-            guild_id.to_string(),
-            board_name.to_string(),
-            source_id.to_string(),
-        ),
+            WHERE board_id = ? AND source_id = ?",
+        params![reaction_count, board_id, source_id.to_string()],
     )?;
 
     Ok(())
@@ -305,60 +849,45 @@ pub fn update_message_reaction_count(
 pub fn get_guild_boards(guild_id: impl ToString) -> Result<Vec<Board>> {
     let conn = get_connection()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT name, reactions, min_reactions, dest_channel
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
             FROM boards
             WHERE guild_id = ?",
-    )?;
+        BOARD_COLUMNS
+    ))?;
 
-    stmt.query_map([guild_id.to_string()], |row| {
-        Ok(Board {
-            name: row.get(0)?,
-            reactions: row.get(1)?,
-            min_reactions: row.get(2)?,
-            dest_channel: row.get(3)?,
-        })
-    })?
-    .collect::<Result<Vec<Board>>>()
+    stmt.query_map([guild_id.to_string()], board_from_row)?
+        .collect::<Result<Vec<Board>>>()
 }
 
 // get a board by name
 pub fn get_board(guild_id: impl ToString, board_name: impl ToString) -> Result<Board> {
     let conn = get_connection()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT name, reactions, min_reactions, dest_channel
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
             FROM boards
             WHERE guild_id = ? AND name = ?",
-    )?;
+        BOARD_COLUMNS
+    ))?;
 
-    stmt.query_row([guild_id.to_string(), board_name.to_string()], |row| {
-        Ok(Board {
-            name: row.get(0)?,
-            reactions: row.get(1)?,
-            min_reactions: row.get(2)?,
-            dest_channel: row.get(3)?,
-        })
-    })
+    stmt.query_row(
+        [guild_id.to_string(), board_name.to_string()],
+        board_from_row,
+    )
 }
 
 pub fn get_board_by_id(board_id: impl ToString) -> Result<Board> {
     let conn = get_connection()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT name, reactions, min_reactions, dest_channel
-                FROM boards
-                WHERE board_id = ?",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+            FROM boards
+            WHERE board_id = ?",
+        BOARD_COLUMNS
+    ))?;
 
-    stmt.query_row([board_id.to_string()], |row| {
-        Ok(Board {
-            name: row.get(0)?,
-            reactions: row.get(1)?,
-            min_reactions: row.get(2)?,
-            dest_channel: row.get(3)?,
-        })
-    })
+    stmt.query_row([board_id.to_string()], board_from_row)
 }
 
 pub fn get_board_user_reactions(
@@ -370,7 +899,8 @@ pub fn get_board_user_reactions(
     let mut stmt = conn.prepare(
         "SELECT user_id, reaction_count
             FROM messages
-            WHERE board_id = (SELECT board_id FROM boards WHERE guild_id = ? AND name = ?)",
+            WHERE board_id = (SELECT board_id FROM boards WHERE guild_id = ? AND name = ?)
+                AND posted = 1",
     )?;
 
     let mut user_counts = std::collections::HashMap::new();
@@ -402,7 +932,8 @@ pub fn get_guild_user_reactions(guild_id: impl ToString) -> Result<Vec<(UserId,
     let mut stmt = conn.prepare(
         "SELECT user_id, reaction_count
             FROM messages
-            WHERE board_id = (SELECT board_id FROM boards WHERE guild_id = ?)",
+            WHERE board_id IN (SELECT board_id FROM boards WHERE guild_id = ?)
+                AND posted = 1",
     )?;
 
     let mut user_counts = std::collections::HashMap::new();
@@ -423,3 +954,139 @@ pub fn get_guild_user_reactions(guild_id: impl ToString) -> Result<Vec<(UserId,
 
     Ok(user_counts.into_iter().collect::<Vec<(UserId, u64)>>())
 }
+
+// get the channel and category rules of a board
+pub fn get_board_channels(board_id: i64) -> Result<Vec<(String, ChannelRule)>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT channel_id, rule
+            FROM board_channels
+            WHERE board_id = ?",
+    )?;
+
+    stmt.query_map([board_id], |row| {
+        Ok((
+            row.get::<usize, String>(0)?,
+            ChannelRule::from_name(&row.get::<usize, String>(1)?).unwrap_or(ChannelRule::Exclude),
+        ))
+    })?
+    .collect::<Result<Vec<(String, ChannelRule)>>>()
+}
+
+// add a channel rule to a board, replacing the rule the channel already had
+pub fn set_board_channel(
+    board_id: i64,
+    channel_id: impl ToString,
+    rule: ChannelRule,
+) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO board_channels
+            (board_id, channel_id, rule)
+            VALUES (?, ?, ?)
+            ON CONFLICT(board_id, channel_id) DO UPDATE
+                SET rule = excluded.rule",
+        params![board_id, channel_id.to_string(), rule.name()],
+    )?;
+
+    Ok(())
+}
+
+// remove the rule of a channel from a board, returns false if it had none
+pub fn remove_board_channel(board_id: i64, channel_id: impl ToString) -> Result<bool> {
+    let conn = get_connection()?;
+
+    let removed = conn.execute(
+        "DELETE FROM board_channels
+            WHERE board_id = ? AND channel_id = ?",
+        params![board_id, channel_id.to_string()],
+    )?;
+
+    Ok(removed > 0)
+}
+
+// get the role rules of a board
+pub fn get_board_roles(board_id: i64) -> Result<Vec<BoardRole>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT role_id, rule, weight
+            FROM board_roles
+            WHERE board_id = ?",
+    )?;
+
+    stmt.query_map([board_id], |row| {
+        Ok(BoardRole {
+            role_id: row.get(0)?,
+            rule: RoleRule::from_name(&row.get::<usize, String>(1)?).unwrap_or(RoleRule::Exclude),
+            weight: row.get(2)?,
+        })
+    })?
+    .collect::<Result<Vec<BoardRole>>>()
+}
+
+// add a role rule to a board, replacing the rule the role already had
+pub fn set_board_role(
+    board_id: i64,
+    role_id: impl ToString,
+    rule: RoleRule,
+    weight: i64,
+) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO board_roles
+            (board_id, role_id, rule, weight)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(board_id, role_id) DO UPDATE
+                SET rule = excluded.rule,
+                    weight = excluded.weight",
+        params![board_id, role_id.to_string(), rule.name(), weight],
+    )?;
+
+    Ok(())
+}
+
+// remove the rule of a role from a board, returns false if it had none
+pub fn remove_board_role(board_id: i64, role_id: impl ToString) -> Result<bool> {
+    let conn = get_connection()?;
+
+    let removed = conn.execute(
+        "DELETE FROM board_roles
+            WHERE board_id = ? AND role_id = ?",
+        params![board_id, role_id.to_string()],
+    )?;
+
+    Ok(removed > 0)
+}
+
+// save the webhook URL of a board once its webhook was created
+pub fn set_board_webhook(board_id: i64, webhook_url: &str) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE boards
+            SET webhook_url = ?
+            WHERE board_id = ?",
+        params![webhook_url, board_id],
+    )?;
+
+    Ok(())
+}
+
+// clear the webhook URL of a board if it is still the given one, so a new webhook is created
+// when it is next needed
+pub fn forget_board_webhook(board_id: i64, webhook_url: &str) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE boards
+            SET webhook_url = NULL
+            WHERE board_id = ? AND webhook_url = ?",
+        params![board_id, webhook_url],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use poise::serenity_prelude::MessageId;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::{Instant, sleep_until};
+
+// time without new events before a recount runs
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+// longest a recount can be pushed back by a steady stream of events
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+struct Pending {
+    first_event: Instant,
+    last_event: Instant,
+    // throttled messages aren't recounted before their author may post again
+    not_before: Option<Instant>,
+}
+
+impl Pending {
+    fn deadline(&self) -> Instant {
+        let deadline = (self.last_event + QUIET_PERIOD).min(self.first_event + MAX_DELAY);
+
+        self.not_before
+            .map_or(deadline, |not_before| deadline.max(not_before))
+    }
+}
+
+// collapses bursts of reaction events into a single recount per board and message
+#[derive(Clone, Default)]
+pub struct RecountQueue {
+    pending: Arc<Mutex<HashMap<(i64, MessageId), Pending>>>,
+}
+
+impl RecountQueue {
+    // record an event, returns true if no recount was pending and the caller should
+    // wait for it with `settle` and then run it
+    pub fn push(&self, board_id: i64, message_id: MessageId) -> bool {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+
+        match pending.get_mut(&(board_id, message_id)) {
+            Some(entry) => {
+                entry.last_event = now;
+                false
+            }
+            None => {
+                pending.insert(
+                    (board_id, message_id),
+                    Pending {
+                        first_event: now,
+                        last_event: now,
+                        not_before: None,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    // hold back the next recount of a board and message until a time, returns true if no
+    // recount was pending and the caller should wait for it with `settle` and then run it,
+    // events arriving in the meantime are folded into the held recount
+    pub fn hold(&self, board_id: i64, message_id: MessageId, until: Instant) -> bool {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+
+        match pending.get_mut(&(board_id, message_id)) {
+            Some(entry) => {
+                entry.not_before = Some(until);
+                false
+            }
+            None => {
+                pending.insert(
+                    (board_id, message_id),
+                    Pending {
+                        first_event: now,
+                        last_event: now,
+                        not_before: Some(until),
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    // wait until the events for a board and message have settled, events arriving after this
+    // returns schedule a new recount
+    pub async fn settle(&self, board_id: i64, message_id: MessageId) {
+        loop {
+            let deadline = match self.pending.lock().unwrap().get(&(board_id, message_id)) {
+                Some(entry) => entry.deadline(),
+                None => return,
+            };
+
+            sleep_until(deadline).await;
+
+            let mut pending = self.pending.lock().unwrap();
+            if pending
+                .get(&(board_id, message_id))
+                .is_none_or(|entry| entry.deadline() <= Instant::now())
+            {
+                pending.remove(&(board_id, message_id));
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use poise::serenity_prelude::{MessageId, UserId};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+// async locks keyed by source message, so the board posts of a message are only ever
+// created, edited or removed by one task at a time
+pub type MessageLocks = Locks<MessageId>;
+
+// async locks keyed by board and author, so the author limits of a board are checked and
+// the board post claimed by one task at a time for each author
+pub type AuthorLocks = Locks<(i64, UserId)>;
+
+#[derive(Clone)]
+pub struct Locks<K> {
+    locks: Arc<Mutex<HashMap<K, Arc<AsyncMutex<()>>>>>,
+}
+
+impl<K> Default for Locks<K> {
+    fn default() -> Self {
+        Self {
+            locks: Arc::default(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> Locks<K> {
+    pub async fn lock(&self, key: K) -> Guard<K> {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        Guard {
+            _guard: lock.lock_owned().await,
+            locks: self.clone(),
+            key,
+        }
+    }
+}
+
+pub struct Guard<K: Clone + Eq + Hash> {
+    _guard: OwnedMutexGuard<()>,
+    locks: Locks<K>,
+    key: K,
+}
+
+impl<K: Clone + Eq + Hash> Drop for Guard<K> {
+    // forget the lock once nobody else holds or waits for it, the map keeps one reference
+    // and this guard the other
+    fn drop(&mut self) {
+        let mut locks = self.locks.locks.lock().unwrap();
+        if locks
+            .get(&self.key)
+            .is_some_and(|lock| Arc::strong_count(lock) <= 2)
+        {
+            locks.remove(&self.key);
+        }
+    }
+}
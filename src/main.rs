@@ -2,30 +2,84 @@ use poise::serenity_prelude as serenity;
 use poise::{Framework, FrameworkOptions};
 use serenity::{
     Client, Context as SerenityContext, GatewayIntents,
-    all::{Color, CreateEmbed, CreateEmbedAuthor, CreateMessage, EditMessage, MessageId, Reaction},
+    all::{ChannelId, GuildId, MessageId, Reaction, ReactionType, Timestamp, User, UserId},
 };
-use std::env;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    time::Duration,
+};
+use tokio::time::Instant;
 
 mod commands;
 pub mod db;
+mod debounce;
+mod locks;
+mod members;
+mod posts;
+mod render;
+
+// discord error code for a user that isn't a member of the server
+const UNKNOWN_MEMBER: isize = 10007;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 
-pub struct Data {}
+#[derive(Clone)]
+pub struct Data {
+    recounts: debounce::RecountQueue,
+    locks: locks::MessageLocks,
+    author_locks: locks::AuthorLocks,
+    member_roles: members::MemberRoles,
+}
 
 async fn event_handler(
     ctx: &serenity::Context,
     event: &serenity::FullEvent,
     _framework: poise::FrameworkContext<'_, Data, Error>,
-    _data: &Data,
+    data: &Data,
 ) -> Result<(), Error> {
     match event {
         serenity::FullEvent::Ready { data_about_bot, .. } => {
             println!("{} is connected!", data_about_bot.user.name)
         }
         serenity::FullEvent::ReactionAdd { add_reaction } => {
-            handle_reaction_add(ctx, add_reaction.clone()).await?
+            handle_reaction(ctx, data, add_reaction.clone()).await?
+        }
+        serenity::FullEvent::ReactionRemove { removed_reaction } => {
+            handle_reaction(ctx, data, removed_reaction.clone()).await?
+        }
+        serenity::FullEvent::ReactionRemoveEmoji { removed_reactions } => {
+            handle_reactions_cleared(
+                ctx,
+                data,
+                removed_reactions.channel_id,
+                removed_reactions.message_id,
+                Some(removed_reactions.emoji.clone()),
+            )
+            .await?
+        }
+        serenity::FullEvent::ReactionRemoveAll {
+            channel_id,
+            removed_from_message_id,
+        } => {
+            handle_reactions_cleared(ctx, data, *channel_id, *removed_from_message_id, None).await?
+        }
+        serenity::FullEvent::MessageUpdate { event, .. } => {
+            if let Some(guild_id) = event.guild_id {
+                handle_message_update(ctx, data, guild_id, event.channel_id, event.id).await?
+            }
+        }
+        serenity::FullEvent::MessageDelete {
+            deleted_message_id, ..
+        } => handle_message_delete(ctx, data, *deleted_message_id).await?,
+        serenity::FullEvent::MessageDeleteBulk {
+            multiple_deleted_messages_ids,
+            ..
+        } => {
+            for deleted_message_id in multiple_deleted_messages_ids {
+                handle_message_delete(ctx, data, *deleted_message_id).await?
+            }
         }
 
         _ => {}
@@ -33,191 +87,791 @@ async fn event_handler(
     Ok(())
 }
 
-async fn handle_reaction_add(ctx: &SerenityContext, added: Reaction) -> Result<(), Error> {
-    let message = match added.message(&ctx.http).await {
-        Ok(message) => message,
-        Err(e) => {
-            println!("Error getting message: {}", e);
-            return Ok(());
-        }
+// recount the boards using the reacted emoji and create or update their posts for the message,
+// shared by reaction adds and removes so board posts go down as well as up
+async fn handle_reaction(
+    ctx: &SerenityContext,
+    data: &Data,
+    reaction: Reaction,
+) -> Result<(), Error> {
+    let guild_id = match reaction.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
     };
 
-    let guild_id = match added.guild_id {
-        Some(id) => id,
+    if db::counts_board_reactions(guild_id)?
+        && let Some((board, post)) = db::get_dest_post(reaction.message_id)?
+    {
+        // the bot's own reactions only seed its board posts
+        if reaction.user_id != Some(ctx.cache.current_user().id) {
+            schedule_board_post_recount(ctx, data, guild_id, &board, &post, Some(&reaction.emoji));
+        }
+    }
+
+    for board in db::find_reaction_boards(guild_id.to_string(), reaction.emoji)? {
+        schedule_recount(
+            ctx,
+            data,
+            board.board_id,
+            guild_id,
+            reaction.channel_id,
+            reaction.message_id,
+        );
+    }
+
+    Ok(())
+}
+
+// a moderator removed every reaction of one emoji, or every reaction if emoji is None,
+// so the affected boards are recounted without them
+async fn handle_reactions_cleared(
+    ctx: &SerenityContext,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    emoji: Option<ReactionType>,
+) -> Result<(), Error> {
+    let guild_id = match channel_id.to_channel(ctx).await?.guild() {
+        Some(channel) => channel.guild_id,
         None => return Ok(()),
     };
 
-    let users = message
-        .channel_id
-        .reaction_users(
-            ctx.http.clone(),
-            message.id,
-            added.emoji.clone(),
-            None,
-            None,
-        )
-        .await?;
+    if db::counts_board_reactions(guild_id)?
+        && let Some((board, post)) = db::get_dest_post(message_id)?
+    {
+        schedule_board_post_recount(ctx, data, guild_id, &board, &post, emoji.as_ref());
+    }
 
-    let count = if users.contains(&message.author) {
-        users.len() - 1
-    } else {
-        users.len()
+    let boards = match emoji {
+        Some(emoji) => db::find_reaction_boards(guild_id.to_string(), emoji)?,
+        None => db::get_guild_boards(guild_id)?,
     };
 
-    let min_reactions = db::find_min_reactions(guild_id.to_string(), added.emoji)?;
-    let qualifying_boards = min_reactions
-        .iter()
-        .filter(|&(_, min, _)| min <= &count)
-        .collect::<Vec<_>>();
+    for board in boards {
+        schedule_recount(ctx, data, board.board_id, guild_id, channel_id, message_id);
+    }
 
-    let guild_channels = ctx.http.get_channels(guild_id).await?;
-
-    for (board_name, _, dest_channel_id) in qualifying_boards.iter() {
-        match db::get_message_dest(guild_id.to_string(), message.id.to_string()) {
-            Ok(dest_id) => {
-                // update message
-                let dest_channel = guild_channels
-                    .iter()
-                    .find(|channel| channel.id.to_string() == dest_channel_id.to_string())
-                    .ok_or("Destination channel not found")?;
-
-                let edit_message = EditMessage::new().content(format!(
-                    "{} **| {} Reactions |** <#{}> **({})**",
-                    board_name, count, message.channel_id, message.author
-                ));
-
-                if let Err(err) = ctx
-                    .http
-                    .edit_message(
-                        dest_channel.id,
-                        MessageId::new(dest_id.parse::<u64>()?),
-                        &edit_message,
-                        Vec::new(),
-                    )
-                    .await
-                {
-                    println!("Error editing message: {}", err);
+    Ok(())
+}
+
+// reactions on a board post are recounted with the source message on boards that count them,
+// posts saved before source channels were recorded can't be traced back to their source
+fn schedule_board_post_recount(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    board: &db::Board,
+    post: &db::Message,
+    emoji: Option<&ReactionType>,
+) {
+    if !board.count_board_reactions {
+        return;
+    }
+    if let Some(emoji) = emoji
+        && !db::from_csv(board.reactions.clone())
+            .iter()
+            .any(|board_emoji| same_emoji(board_emoji, emoji))
+    {
+        return;
+    }
+
+    let (Some(source_channel), Ok(source_id)) = (
+        post.source_channel
+            .as_ref()
+            .and_then(|channel_id| channel_id.parse::<u64>().ok()),
+        post.source_id.parse::<u64>(),
+    ) else {
+        return;
+    };
+
+    schedule_recount(
+        ctx,
+        data,
+        board.board_id,
+        guild_id,
+        ChannelId::new(source_channel),
+        MessageId::new(source_id),
+    );
+}
+
+// queue a recount of a board for a message, bursts of reaction events are collapsed into one
+// recount and one board post edit once the reactions settle
+fn schedule_recount(
+    ctx: &SerenityContext,
+    data: &Data,
+    board_id: i64,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) {
+    if !data.recounts.push(board_id, message_id) {
+        return;
+    }
+
+    let ctx = ctx.clone();
+    let data = data.clone();
+
+    tokio::spawn(async move {
+        loop {
+            data.recounts.settle(board_id, message_id).await;
+
+            let result = {
+                let _guard = data.locks.lock(message_id).await;
+                recount_board(&ctx, &data, board_id, guild_id, channel_id, message_id).await
+            };
+
+            // throttled messages are queued in memory and recounted once their author may post
+            // again, a queue lost to a restart is picked up by the next reaction
+            match result {
+                Ok(Some(wait))
+                    if data
+                        .recounts
+                        .hold(board_id, message_id, Instant::now() + wait) => {}
+                Ok(_) => return,
+                Err(err) => {
+                    println!("Error updating board {}: {}", board_id, err);
+                    return;
                 }
+            }
+        }
+    });
+}
 
-                db::update_message_reaction_count(guild_id, board_name, dest_id, count as i64)?;
+// recount a board for a message and update its board post, returns how long to wait before
+// recounting a message that is queued by the board's author limits
+async fn recount_board(
+    ctx: &SerenityContext,
+    data: &Data,
+    board_id: i64,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<Option<Duration>, Error> {
+    // the board may have been deleted while the recount was queued
+    let board = match db::get_board_by_id(board_id) {
+        Ok(board) => board,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    if !is_source_channel(ctx, &board, channel_id).await? {
+        return Ok(None);
+    }
+
+    let message = fetch_message(ctx, guild_id, channel_id, message_id).await?;
+    let count = count_reactions(ctx, data, &message, &board).await?;
+
+    update_board_post(ctx, data, &message, &board, count).await
+}
+
+// whether a board takes messages from a channel, the rule of the channel itself wins over the
+// rule of its parent channel for threads and of its category, the board's own channel is
+// excluded unless it has a rule, and boards with include rules only take included channels
+async fn is_source_channel(
+    ctx: &SerenityContext,
+    board: &db::Board,
+    channel_id: ChannelId,
+) -> Result<bool, Error> {
+    let rules = db::get_board_channels(board.board_id)?;
+
+    let mut current = Some(channel_id);
+    while let Some(channel_id) = current {
+        if let Some((_, rule)) = rules
+            .iter()
+            .find(|(rule_channel, _)| *rule_channel == channel_id.to_string())
+        {
+            return Ok(*rule == db::ChannelRule::Include);
+        }
+
+        if channel_id.to_string() == board.dest_channel {
+            return Ok(false);
+        }
+
+        current = channel_id
+            .to_channel(ctx)
+            .await?
+            .guild()
+            .and_then(|channel| channel.parent_id);
+    }
+
+    Ok(!rules
+        .iter()
+        .any(|(_, rule)| *rule == db::ChannelRule::Include))
+}
+
+// create, edit or remove the board post of a message according to its new reaction count,
+// returns how long to wait before retrying a message queued by the board's author limits
+async fn update_board_post(
+    ctx: &SerenityContext,
+    data: &Data,
+    message: &serenity::Message,
+    board: &db::Board,
+    count: usize,
+) -> Result<Option<Duration>, Error> {
+    let below_removal = board
+        .remove_below
+        .is_some_and(|remove_below| count < remove_below as usize);
+
+    match db::get_board_post(board.board_id, message.id) {
+        Ok(Some(post)) if below_removal => {
+            // remove message from board
+            if let Err(err) = posts::delete_post(&ctx.http, board, &post).await {
+                println!("Error deleting message: {}", err);
             }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                // create new message
-                let dest_message = create_board_message(&message, board_name, count)?;
-
-                // send to destination channel
-                let channel = guild_channels
-                    .iter()
-                    .find(|channel| &channel.id.to_string() == dest_channel_id)
-                    .ok_or("Destination channel not found")?;
-
-                let dest_msg = channel.send_message(&ctx.http, dest_message).await?;
-
-                // save to database
-                db::add_message(
-                    guild_id,
-                    board_name,
-                    message.author.id,
-                    message.id,
-                    dest_msg.id,
-                    count as i64,
-                )?;
+
+            db::unpost_message(board.board_id, message.id, count as i64)?;
+        }
+        Ok(Some(post)) => {
+            // update message
+            if let Err(err) = posts::edit_post(&ctx.http, board, &post, message, count).await {
+                println!("Error editing message: {}", err);
             }
-            Err(err) => {
-                println!("Database error: {}", err);
+
+            db::update_message_reaction_count(board.board_id, message.id, count as i64)?;
+        }
+        // messages past the board's age limit aren't posted anymore, posts they already have
+        // keep following their reactions
+        Ok(None) | Err(rusqlite::Error::QueryReturnedNoRows)
+            if count >= board.min_reactions as usize && !is_too_old(board, message.timestamp) =>
+        {
+            // messages dropped by the board's author limits stay off the board
+            if db::is_message_throttled(board.board_id, message.id)? {
+                db::update_message_reaction_count(board.board_id, message.id, count as i64)?;
+                return Ok(None);
             }
+
+            // the limits are checked and the message claimed under one lock, so messages by the
+            // same author being recounted at once can't both pass the limits
+            let _guard = data
+                .author_locks
+                .lock((board.board_id, message.author.id))
+                .await;
+
+            match author_throttle(board, message.author.id)? {
+                // the message waits off the board while its author is over the board's limits
+                Some(wait) if board.throttle_policy == db::ThrottlePolicy::Queue => {
+                    db::update_message_reaction_count(board.board_id, message.id, count as i64)?;
+                    return Ok(Some(wait));
+                }
+                Some(_) => {
+                    db::throttle_message(
+                        board.board_id,
+                        message.author.id,
+                        message.id,
+                        message.channel_id,
+                        count as i64,
+                    )?;
+                }
+                None => {
+                    create_board_post(ctx, message, board, count).await?;
+                }
+            }
+        }
+        Ok(None) => {
+            db::update_message_reaction_count(board.board_id, message.id, count as i64)?;
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+        Err(err) => {
+            println!("Database error: {}", err);
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
-pub fn create_board_message(
+// how long the author of a message has to wait before the board takes another of their
+// messages, None if the board's author limit and cooldown allow one now
+fn author_throttle(board: &db::Board, author_id: UserId) -> Result<Option<Duration>, Error> {
+    let limit = board.author_limit.filter(|limit| *limit > 0);
+    let cooldown = board.author_cooldown.filter(|cooldown| *cooldown > 0);
+    if limit.is_none() && cooldown.is_none() {
+        return Ok(None);
+    }
+
+    let now = Timestamp::now().unix_timestamp();
+    let since = now
+        - limit
+            .map_or(0, |_| board.author_window)
+            .max(cooldown.unwrap_or(0));
+    let post_times = db::get_author_post_times(board.board_id, author_id, since)?;
+
+    Ok(throttle_wait(board, now, &post_times))
+}
+
+// how long an author has to wait at the unix time now, given the times their messages were
+// posted to the board oldest first, None if they are within the board's limits
+fn throttle_wait(board: &db::Board, now: i64, post_times: &[i64]) -> Option<Duration> {
+    let limit = board.author_limit.filter(|limit| *limit > 0);
+    let cooldown = board.author_cooldown.filter(|cooldown| *cooldown > 0);
+
+    // the author may post again once the oldest post keeping them at the limit leaves the window
+    let limit_until = limit.and_then(|limit| {
+        let in_window = post_times
+            .iter()
+            .filter(|posted_at| **posted_at > now - board.author_window)
+            .collect::<Vec<_>>();
+
+        in_window
+            .len()
+            .checked_sub(limit as usize)
+            .map(|index| in_window[index] + board.author_window)
+    });
+    let cooldown_until = cooldown.and_then(|cooldown| {
+        post_times
+            .last()
+            .map(|posted_at| posted_at + cooldown)
+            .filter(|until| *until > now)
+    });
+
+    limit_until
+        .max(cooldown_until)
+        .map(|until| Duration::from_secs((until - now).max(1) as u64))
+}
+
+// post a message to a board, returns false if it is already posted or can't be posted to the
+// board because of its nsfw setting
+async fn create_board_post(
+    ctx: &SerenityContext,
     message: &serenity::Message,
-    board_name: impl AsRef<str>,
+    board: &db::Board,
     count: usize,
-) -> Result<CreateMessage, Error> {
-    let mut dest_message = CreateMessage::new().content(format!(
-        "{} **| {} Reactions |** <#{}> **({})**",
-        board_name.as_ref(),
-        count,
+) -> Result<bool, Error> {
+    let dest_channel = ChannelId::new(board.dest_channel.parse::<u64>()?);
+
+    // keep messages from nsfw channels off boards that aren't nsfw themselves
+    if is_nsfw(ctx, message.channel_id).await? && !is_nsfw(ctx, dest_channel).await? {
+        return Ok(false);
+    }
+
+    // claim the message first so it can't be posted twice
+    if !db::claim_message(
+        board.board_id,
+        message.author.id,
+        message.id,
         message.channel_id,
-        message.author
-    ));
-
-    // handle reply
-    if let Some(referenced_message) = message.referenced_message.clone() {
-        dest_message = dest_message.add_embed({
-            let mut embed = CreateEmbed::new()
-                .author(
-                    CreateEmbedAuthor::new(format!(
-                        "Replying to {}",
-                        referenced_message.author.name
-                    ))
-                    .url(referenced_message.link())
-                    .icon_url(referenced_message.author.avatar_url().unwrap_or_default()),
-                )
-                .description(referenced_message.content.to_string())
-                .timestamp(referenced_message.timestamp);
-
-            if let Some(attachment) = referenced_message.attachments.first() {
-                embed = embed.image(attachment.url.to_string());
+        count as i64,
+    )? {
+        return Ok(false);
+    }
+
+    // send to destination channel, releasing the claim if that fails
+    let sent = match posts::send_post(&ctx.http, message, board, count).await {
+        Ok(sent) => sent,
+        Err(err) => {
+            db::unpost_message(board.board_id, message.id, count as i64)?;
+            return Err(err);
+        }
+    };
+
+    // save to database
+    db::set_message_dest(
+        board.board_id,
+        message.id,
+        sent.dest_id,
+        sent.webhook_url.clone(),
+    )?;
+
+    if board.count_board_reactions
+        && let Err(err) = posts::seed_reactions(&ctx.http, board, &sent).await
+    {
+        println!("Error adding board reactions: {}", err);
+    }
+
+    match sent.thread_id {
+        Some(thread_id) => db::set_message_thread(board.board_id, message.id, thread_id)?,
+        None if board.create_threads => {
+            match posts::open_thread(&ctx.http, board, sent.dest_id, message).await {
+                Ok(thread_id) => db::set_message_thread(board.board_id, message.id, thread_id)?,
+                Err(err) => println!("Error creating thread: {}", err),
             }
+        }
+        None => {}
+    }
+
+    Ok(true)
+}
+
+// boards with an age limit don't take messages sent longer ago than the limit
+fn is_too_old(board: &db::Board, sent_at: Timestamp) -> bool {
+    board
+        .max_message_age
+        .filter(|max_age| *max_age > 0)
+        .is_some_and(|max_age| {
+            Timestamp::now().unix_timestamp() - sent_at.unix_timestamp() > max_age
+        })
+}
+
+// threads are nsfw if their parent channel is
+async fn is_nsfw(ctx: &SerenityContext, channel_id: ChannelId) -> Result<bool, Error> {
+    let channel = match channel_id.to_channel(ctx).await? {
+        serenity::Channel::Guild(channel) => channel,
+        _ => return Ok(false),
+    };
+
+    match channel.parent_id {
+        Some(parent_id) if channel.thread_metadata.is_some() => Ok(parent_id
+            .to_channel(ctx)
+            .await?
+            .guild()
+            .is_some_and(|parent| parent.nsfw)),
+        _ => Ok(channel.nsfw),
+    }
+}
+
+// re-render the board posts of an edited message
+async fn handle_message_update(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let _guard = data.locks.lock(message_id).await;
 
-            embed
-        });
+    let posts = db::get_source_posts(message_id)?;
+    if posts.is_empty() {
+        return Ok(());
+    }
 
-        // add additional attachments as embeds
-        for attachment in referenced_message.attachments.iter().skip(1) {
-            dest_message = dest_message.add_embed(
-                CreateEmbed::new()
-                    .image(attachment.url.to_string())
-                    .color(Color::from_rgb(0x1d, 0xa0, 0xf2)),
-            );
+    let message = match fetch_message(ctx, guild_id, channel_id, message_id).await {
+        Ok(message) => message,
+        Err(e) => {
+            println!("Error getting message: {}", e);
+            return Ok(());
         }
+    };
 
-        // add referenced message embeds
-        for embed in referenced_message.embeds {
-            dest_message = dest_message
-                .add_embed(CreateEmbed::from(embed).color(Color::from_rgb(0x63, 0x63, 0xff)));
+    for (board, post) in posts {
+        if let Err(err) = posts::edit_post(
+            &ctx.http,
+            &board,
+            &post,
+            &message,
+            post.reaction_count as usize,
+        )
+        .await
+        {
+            println!("Error editing message: {}", err);
         }
     }
 
-    // add main message embed
-    dest_message = dest_message.add_embed({
-        let mut embed = CreateEmbed::new()
-            .author(
-                CreateEmbedAuthor::new(&message.author.name)
-                    .url(message.link())
-                    .icon_url(message.author.avatar_url().unwrap_or_default()),
-            )
-            .description(message.content.to_string())
-            .color(Color::from_rgb(0xff, 0xe1, 0x9c))
-            .timestamp(message.timestamp);
+    Ok(())
+}
+
+// messages fetched over HTTP don't carry their guild ID, which jump links need
+async fn fetch_message(
+    ctx: &SerenityContext,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<serenity::Message, Error> {
+    let mut message = ctx.http.get_message(channel_id, message_id).await?;
+
+    message.guild_id = Some(guild_id);
+    if let Some(referenced_message) = message.referenced_message.as_mut() {
+        referenced_message.guild_id = Some(guild_id);
+    }
 
-        if let Some(attachment) = message.attachments.first() {
-            embed = embed.image(attachment.url.to_string());
+    Ok(message)
+}
+
+// delete or tombstone the board posts of a deleted message according to each board's policy
+async fn handle_message_delete(
+    ctx: &SerenityContext,
+    data: &Data,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let _guard = data.locks.lock(message_id).await;
+
+    for (board, post) in db::get_source_posts(message_id)? {
+        match board.on_delete {
+            db::DeletePolicy::Delete => {
+                if let Err(err) = posts::delete_post(&ctx.http, &board, &post).await {
+                    println!("Error deleting message: {}", err);
+                }
+
+                db::unpost_message(board.board_id, message_id, post.reaction_count)?;
+            }
+            db::DeletePolicy::Tombstone => {
+                if let Err(err) = posts::tombstone_post(&ctx.http, &board, &post).await {
+                    println!("Error editing message: {}", err);
+                }
+            }
+            db::DeletePolicy::Keep => {}
         }
+    }
 
-        embed
-    });
+    Ok(())
+}
 
-    // add additional attachments
-    for attachment in message.attachments.iter().skip(1) {
-        dest_message = dest_message.add_embed(
-            CreateEmbed::new()
-                .image(attachment.url.to_string())
-                .color(Color::from_rgb(0x1d, 0xa0, 0xf2)),
-        );
+// count the reactions of a message for a board, applying its self-star and bot policies,
+// reactions on the board post count too on boards that count them
+async fn count_reactions(
+    ctx: &SerenityContext,
+    data: &Data,
+    message: &serenity::Message,
+    board: &db::Board,
+) -> Result<usize, Error> {
+    let role_rules = db::get_board_roles(board.board_id)?;
+
+    let board_post = match db::get_board_post(board.board_id, message.id) {
+        Ok(Some(post)) if board.count_board_reactions => Some((
+            posts::post_channel(board, &post)?,
+            MessageId::new(post.dest_id.parse::<u64>()?),
+        )),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    // reactor identities are only fetched when the board settings need them,
+    // otherwise the counts already on the message are used, reactors of the board post
+    // are always fetched so reactions on both messages are only counted once
+    let needs_users = board.count_mode == db::CountMode::UniqueUsers
+        || board.bot_reactions == db::BotPolicy::Ignore
+        || !role_rules.is_empty()
+        || board_post.is_some();
+
+    let mut emoji_counts = Vec::new();
+    let mut reactors = HashSet::new();
+    let mut weights = HashMap::new();
+
+    for emoji in db::from_csv(board.reactions.clone()) {
+        let reaction = message
+            .reactions
+            .iter()
+            .find(|reaction| same_emoji(&reaction.reaction_type, &emoji));
+
+        // the board post is seeded with every board emoji, the source may have none of them
+        if reaction.is_none() && board_post.is_none() {
+            continue;
+        }
+
+        // messages the author reacted to, their reaction is removed from each of them
+        let mut author_reactions = Vec::new();
+
+        let (author_reacted, emoji_reactors) = if needs_users {
+            let mut users = match reaction {
+                Some(_) => {
+                    fetch_reaction_users(ctx, message.channel_id, message.id, &emoji).await?
+                }
+                None => Vec::new(),
+            };
+            if users.iter().any(|user| user.id == message.author.id) {
+                author_reactions.push((message.channel_id, message.id));
+            }
+
+            if let Some((channel_id, dest_id)) = board_post {
+                // the board post may have been deleted by hand
+                match fetch_reaction_users(ctx, channel_id, dest_id, &emoji).await {
+                    Ok(post_users) => {
+                        if post_users.iter().any(|user| user.id == message.author.id) {
+                            author_reactions.push((channel_id, dest_id));
+                        }
+
+                        // the bot's own reaction only seeds the board post
+                        let bot_id = ctx.cache.current_user().id;
+                        users.extend(post_users.into_iter().filter(|user| user.id != bot_id));
+                    }
+                    Err(err) => println!("Error getting board post reactions: {}", err),
+                }
+            }
+
+            let author_reacted = !author_reactions.is_empty();
+            let emoji_reactors = users
+                .into_iter()
+                .filter(|user| user.id != message.author.id)
+                .filter(|user| !user.bot || board.bot_reactions == db::BotPolicy::Count)
+                .map(|user| user.id)
+                .collect::<HashSet<UserId>>();
+
+            (author_reacted, Some(emoji_reactors))
+        } else if board.self_star != db::SelfStarPolicy::Count {
+            let author_reacted = has_reacted(ctx, message, &emoji, message.author.id).await?;
+            if author_reacted {
+                author_reactions.push((message.channel_id, message.id));
+            }
+
+            (author_reacted, None)
+        } else {
+            (false, None)
+        };
+
+        if board.self_star == db::SelfStarPolicy::Remove {
+            for (channel_id, message_id) in author_reactions {
+                if let Err(err) = channel_id
+                    .delete_reaction(
+                        &ctx.http,
+                        message_id,
+                        Some(message.author.id),
+                        emoji.clone(),
+                    )
+                    .await
+                {
+                    println!("Error removing self reaction: {}", err);
+                }
+            }
+        }
+
+        let author_counted = board.self_star == db::SelfStarPolicy::Count;
+
+        match emoji_reactors {
+            Some(mut emoji_reactors) => {
+                if author_reacted && author_counted {
+                    emoji_reactors.insert(message.author.id);
+                }
+
+                let mut emoji_count = 0;
+                for user_id in &emoji_reactors {
+                    emoji_count +=
+                        reactor_weight(ctx, data, message, &role_rules, &mut weights, *user_id)
+                            .await?;
+                }
+
+                emoji_counts.push(emoji_count);
+                reactors.extend(emoji_reactors);
+            }
+            None => emoji_counts.push(
+                reaction
+                    .map_or(0, |reaction| reaction.count as usize)
+                    .saturating_sub((author_reacted && !author_counted) as usize),
+            ),
+        }
+    }
+
+    Ok(match board.count_mode {
+        db::CountMode::PerEmojiMax => emoji_counts.into_iter().max().unwrap_or(0),
+        db::CountMode::Sum => emoji_counts.into_iter().sum(),
+        db::CountMode::UniqueUsers => reactors
+            .iter()
+            .map(|user_id| weights.get(user_id).copied().unwrap_or(1))
+            .sum(),
+    })
+}
+
+// how many reactions a reaction from a user counts as on a board with role rules, 0 if their
+// roles keep it from counting, each member is only looked up once per recount and their roles
+// are reused by the recounts that follow for a few minutes
+async fn reactor_weight(
+    ctx: &SerenityContext,
+    data: &Data,
+    message: &serenity::Message,
+    rules: &[db::BoardRole],
+    weights: &mut HashMap<UserId, usize>,
+    user_id: UserId,
+) -> Result<usize, Error> {
+    if rules.is_empty() {
+        return Ok(1);
     }
+    if let Some(weight) = weights.get(&user_id) {
+        return Ok(*weight);
+    }
+
+    // members that left the server count as having no roles, other errors fail the recount
+    // rather than miscount it
+    let roles = match message.guild_id {
+        Some(guild_id) => match data.member_roles.get(guild_id, user_id) {
+            Some(roles) => roles,
+            None => {
+                let roles = match guild_id.member(ctx, user_id).await {
+                    Ok(member) => member.roles,
+                    Err(serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(
+                        response,
+                    ))) if response.error.code == UNKNOWN_MEMBER => Vec::new(),
+                    Err(err) => return Err(err.into()),
+                };
+
+                data.member_roles.insert(guild_id, user_id, roles.clone());
+                roles
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let has_role = |rule: &db::BoardRole| roles.iter().any(|role| role.to_string() == rule.role_id);
+
+    let excluded = rules
+        .iter()
+        .any(|rule| rule.rule == db::RoleRule::Exclude && has_role(rule));
+    let required = rules
+        .iter()
+        .filter(|rule| rule.rule == db::RoleRule::Require)
+        .collect::<Vec<_>>();
+    let missing_required = !required.is_empty() && !required.iter().any(|rule| has_role(rule));
+
+    let weight = if excluded || missing_required {
+        0
+    } else {
+        rules
+            .iter()
+            .filter(|rule| rule.rule == db::RoleRule::Weight && has_role(rule))
+            .map(|rule| rule.weight as usize)
+            .max()
+            .unwrap_or(1)
+    };
 
-    // add message embeds
-    for embed in message.embeds.clone() {
-        dest_message = dest_message
-            .add_embed(CreateEmbed::from(embed).color(Color::from_rgb(0x63, 0x63, 0xff)));
+    weights.insert(user_id, weight);
+    Ok(weight)
+}
+
+// fetch every user that reacted with the emoji, paging past the 100 users returned per request
+async fn fetch_reaction_users(
+    ctx: &SerenityContext,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    emoji: &ReactionType,
+) -> Result<Vec<User>, Error> {
+    const PAGE_SIZE: u8 = 100;
+    let mut users: Vec<User> = Vec::new();
+
+    loop {
+        let page = channel_id
+            .reaction_users(
+                &ctx.http,
+                message_id,
+                emoji.clone(),
+                Some(PAGE_SIZE),
+                users.last().map(|user| user.id),
+            )
+            .await?;
+
+        let last_page = page.len() < PAGE_SIZE as usize;
+        users.extend(page);
+
+        if last_page {
+            return Ok(users);
+        }
     }
+}
+
+// reactors are listed in ascending ID order, so the first reactor after the previous ID
+// is the user themselves if they reacted
+async fn has_reacted(
+    ctx: &SerenityContext,
+    message: &serenity::Message,
+    emoji: &ReactionType,
+    user_id: UserId,
+) -> Result<bool, Error> {
+    let users = message
+        .channel_id
+        .reaction_users(
+            &ctx.http,
+            message.id,
+            emoji.clone(),
+            Some(1),
+            UserId::new(user_id.get() - 1),
+        )
+        .await?;
 
-    Ok(dest_message)
+    Ok(users.first().is_some_and(|user| user.id == user_id))
+}
+
+// custom emojis are compared by ID since their name can change after the board was set up
+fn same_emoji(a: &ReactionType, b: &ReactionType) -> bool {
+    match (a, b) {
+        (ReactionType::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a == b,
+        (ReactionType::Unicode(a), ReactionType::Unicode(b)) => a == b,
+        _ => false,
+    }
 }
 
 #[tokio::main]
@@ -233,6 +887,9 @@ async fn main() {
                 commands::deleteboard(),
                 commands::showboard(),
                 commands::editboard(),
+                commands::boardchannels(),
+                commands::boardroles(),
+                commands::forceadd(),
                 commands::leaderboard(),
                 commands::moststarred(),
                 commands::random(),
@@ -256,7 +913,12 @@ async fn main() {
                         println!("Failed to register commands in guild {}: {}", guild.id, e);
                     }
                 }
-                Ok(Data {})
+                Ok(Data {
+                    recounts: debounce::RecountQueue::default(),
+                    locks: locks::MessageLocks::default(),
+                    author_locks: locks::AuthorLocks::default(),
+                    member_roles: members::MemberRoles::default(),
+                })
             })
         })
         .build();
@@ -275,3 +937,82 @@ async fn main() {
         println!("Client error: {why:?}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_000_000;
+
+    fn board(limit: Option<i64>, window: i64, cooldown: Option<i64>) -> db::Board {
+        db::Board {
+            author_limit: limit,
+            author_window: window,
+            author_cooldown: cooldown,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_limits_never_throttle() {
+        let board = board(None, 100, Some(0));
+        assert_eq!(throttle_wait(&board, NOW, &[NOW - 1, NOW]), None);
+    }
+
+    #[test]
+    fn below_limit_is_not_throttled() {
+        let board = board(Some(2), 100, None);
+        assert_eq!(throttle_wait(&board, NOW, &[NOW - 10]), None);
+    }
+
+    #[test]
+    fn exactly_at_limit_waits_for_oldest_post() {
+        let board = board(Some(2), 100, None);
+        assert_eq!(
+            throttle_wait(&board, NOW, &[NOW - 50, NOW - 10]),
+            Some(Duration::from_secs(50))
+        );
+    }
+
+    #[test]
+    fn posts_at_window_edge_left_the_window() {
+        let board = board(Some(2), 100, None);
+        assert_eq!(throttle_wait(&board, NOW, &[NOW - 100, NOW - 10]), None);
+    }
+
+    #[test]
+    fn over_limit_after_force_add_waits_until_below_limit() {
+        // a force-added post can take the author past the limit, then two posts have to leave
+        // the window before the next one fits
+        let board = board(Some(2), 100, None);
+        assert_eq!(
+            throttle_wait(&board, NOW, &[NOW - 90, NOW - 50, NOW - 10]),
+            Some(Duration::from_secs(50))
+        );
+    }
+
+    #[test]
+    fn cooldown_only_waits_from_last_post() {
+        let board = board(None, 100, Some(30));
+        assert_eq!(
+            throttle_wait(&board, NOW, &[NOW - 80, NOW - 10]),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(throttle_wait(&board, NOW, &[NOW - 30]), None);
+    }
+
+    #[test]
+    fn both_limits_wait_for_the_later_one() {
+        let limit_later = board(Some(2), 100, Some(30));
+        assert_eq!(
+            throttle_wait(&limit_later, NOW, &[NOW - 50, NOW - 10]),
+            Some(Duration::from_secs(50))
+        );
+
+        let cooldown_later = board(Some(2), 100, Some(80));
+        assert_eq!(
+            throttle_wait(&cooldown_later, NOW, &[NOW - 50, NOW - 10]),
+            Some(Duration::from_secs(70))
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use poise::serenity_prelude::{GuildId, RoleId, UserId};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+// how long the roles of a member are used before they are looked up again
+const ROLES_TTL: Duration = Duration::from_secs(5 * 60);
+
+// roles of a member along with when they were looked up
+type CachedRoles = (Instant, Vec<RoleId>);
+
+// roles of the reactors looked up for role rules, kept across recounts so busy boards don't
+// look every reactor up again on each reaction
+#[derive(Clone, Default)]
+pub struct MemberRoles {
+    roles: Arc<Mutex<HashMap<(GuildId, UserId), CachedRoles>>>,
+}
+
+impl MemberRoles {
+    pub fn get(&self, guild_id: GuildId, user_id: UserId) -> Option<Vec<RoleId>> {
+        self.roles
+            .lock()
+            .unwrap()
+            .get(&(guild_id, user_id))
+            .filter(|(looked_up, _)| looked_up.elapsed() < ROLES_TTL)
+            .map(|(_, roles)| roles.clone())
+    }
+
+    pub fn insert(&self, guild_id: GuildId, user_id: UserId, roles: Vec<RoleId>) {
+        let mut cached = self.roles.lock().unwrap();
+
+        // forget members that stopped reacting
+        cached.retain(|_, (looked_up, _)| looked_up.elapsed() < ROLES_TTL);
+        cached.insert((guild_id, user_id), (Instant::now(), roles));
+    }
+}
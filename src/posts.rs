@@ -0,0 +1,356 @@
+use crate::{Error, db, render};
+use poise::serenity_prelude::{
+    self as serenity, Builder, ChannelId, ChannelType, Color, CreateEmbed, CreateForumPost,
+    CreateThread, CreateWebhook, EditMessage, EditThread, EditWebhookMessage, ForumEmoji,
+    ForumTagId, GuildChannel, Http, HttpError, Message, MessageId, ReactionType, WebhookId,
+};
+use tokio::sync::Mutex;
+
+// board posts are sent by the bot, or through the board's webhook for boards in webhook mode,
+// and are edited and deleted the same way they were sent, through the webhook that sent them
+// even once the board has moved on to another one
+
+const WEBHOOK_NAME: &str = "crustboard";
+
+// discord error code for a webhook that no longer exists
+const UNKNOWN_WEBHOOK: isize = 10015;
+
+// boards posting several messages at once would otherwise each create a webhook
+static WEBHOOK_CREATION: Mutex<()> = Mutex::const_new(());
+
+// a board post that was just sent
+pub struct SentPost {
+    pub dest_id: MessageId,
+    // the URL of the webhook that sent the post, None if the bot sent it
+    pub webhook_url: Option<String>,
+    // the thread of a post in a forum channel
+    pub thread_id: Option<ChannelId>,
+}
+
+// send a new board post, as a post of its own in forum channels
+pub async fn send_post(
+    http: &Http,
+    message: &Message,
+    board: &db::Board,
+    count: usize,
+) -> Result<SentPost, Error> {
+    let dest_channel = ChannelId::new(board.dest_channel.parse::<u64>()?);
+    let forum = dest_channel
+        .to_channel(http)
+        .await?
+        .guild()
+        .filter(|channel| channel.kind == ChannelType::Forum);
+
+    let tags = match &forum {
+        Some(forum) if board.forum_tags => matching_tags(forum, board, message),
+        _ => Vec::new(),
+    };
+
+    if !board.webhook_mode {
+        let dest_message = render::create_board_message(http, message, board, count).await?;
+
+        if forum.is_none() {
+            return Ok(SentPost {
+                dest_id: dest_channel.send_message(http, dest_message).await?.id,
+                webhook_url: None,
+                thread_id: None,
+            });
+        }
+
+        let thread = dest_channel
+            .create_forum_post(
+                http,
+                CreateForumPost::new(render::create_thread_name(message), dest_message)
+                    .set_applied_tags(tags),
+            )
+            .await?;
+
+        // the starter message of a forum post shares the ID of its thread
+        return Ok(SentPost {
+            dest_id: MessageId::new(thread.id.get()),
+            webhook_url: None,
+            thread_id: Some(thread.id),
+        });
+    }
+
+    let webhook_url = board_webhook(http, board).await?;
+    let (webhook_id, token) = parse_webhook_url(&webhook_url)?;
+    let mut webhook_message = render::create_webhook_message(http, message, board, count).await;
+    if forum.is_some() {
+        webhook_message = webhook_message.thread_name(render::create_thread_name(message));
+    }
+
+    let dest_message = match webhook_message
+        .execute(http, (webhook_id, &token, true))
+        .await
+    {
+        Ok(Some(dest_message)) => dest_message,
+        Ok(None) => return Err("The webhook didn't return the board post".into()),
+        Err(err) => {
+            forget_deleted_webhook(board, &webhook_url, &err)?;
+            return Err(err.into());
+        }
+    };
+
+    // webhooks can't tag the forum posts they create, so the tags are added afterwards
+    let thread_id = forum.map(|_| dest_message.channel_id);
+    if let Some(thread_id) = thread_id
+        && !tags.is_empty()
+        && let Err(err) = thread_id
+            .edit_thread(http, EditThread::new().applied_tags(tags))
+            .await
+    {
+        println!("Error tagging forum post: {}", err);
+    }
+
+    Ok(SentPost {
+        dest_id: dest_message.id,
+        webhook_url: Some(webhook_url),
+        thread_id,
+    })
+}
+
+// forum tags with the emoji of a board reaction the message has, at most the five a post can have
+fn matching_tags(forum: &GuildChannel, board: &db::Board, message: &Message) -> Vec<ForumTagId> {
+    let reacted = db::from_csv(board.reactions.clone())
+        .into_iter()
+        .filter(|emoji| {
+            message
+                .reactions
+                .iter()
+                .any(|reaction| crate::same_emoji(&reaction.reaction_type, emoji))
+        })
+        .collect::<Vec<ReactionType>>();
+
+    forum
+        .available_tags
+        .iter()
+        .filter(|tag| {
+            reacted.iter().any(|emoji| match (&tag.emoji, emoji) {
+                (Some(ForumEmoji::Id(tag_id)), ReactionType::Custom { id, .. }) => tag_id == id,
+                (Some(ForumEmoji::Name(tag_name)), ReactionType::Unicode(name)) => tag_name == name,
+                _ => false,
+            })
+        })
+        .map(|tag| tag.id)
+        .take(5)
+        .collect()
+}
+
+// messages of forum posts live in the post's thread rather than the forum channel
+fn forum_thread(post: &db::Message) -> Option<ChannelId> {
+    post.thread_id
+        .as_ref()
+        .filter(|thread_id| **thread_id == post.dest_id)
+        .and_then(|thread_id| thread_id.parse::<u64>().ok())
+        .map(ChannelId::new)
+}
+
+pub fn post_channel(board: &db::Board, post: &db::Message) -> Result<ChannelId, Error> {
+    match forum_thread(post) {
+        Some(thread_id) => Ok(thread_id),
+        None => Ok(ChannelId::new(board.dest_channel.parse::<u64>()?)),
+    }
+}
+
+// react to a new board post with the board's emojis, so its reactions can be counted toward the
+// source message with a single click
+pub async fn seed_reactions(http: &Http, board: &db::Board, sent: &SentPost) -> Result<(), Error> {
+    let channel_id = match sent.thread_id {
+        Some(thread_id) => thread_id,
+        None => ChannelId::new(board.dest_channel.parse::<u64>()?),
+    };
+
+    for emoji in db::from_csv(board.reactions.clone()) {
+        http.create_reaction(channel_id, sent.dest_id, &emoji)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// re-render a board post, so it follows the count and the source message
+pub async fn edit_post(
+    http: &Http,
+    board: &db::Board,
+    post: &db::Message,
+    message: &Message,
+    count: usize,
+) -> Result<(), Error> {
+    let dest_id = MessageId::new(post.dest_id.parse::<u64>()?);
+
+    if let Some(webhook_url) = &post.sent_webhook_url {
+        let (webhook_id, token) = parse_webhook_url(webhook_url)?;
+        let mut edit_message = render::edit_webhook_message(message, board, count);
+        if let Some(thread_id) = forum_thread(post) {
+            edit_message = edit_message.in_thread(thread_id);
+        }
+
+        if let Err(err) = edit_message
+            .execute(http, (webhook_id, &token, dest_id))
+            .await
+        {
+            forget_deleted_webhook(board, webhook_url, &err)?;
+            return Err(err.into());
+        }
+    } else {
+        http.edit_message(
+            post_channel(board, post)?,
+            dest_id,
+            &render::edit_board_message(message, board, count),
+            Vec::new(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+// replace a board post with a note that its source message was deleted
+pub async fn tombstone_post(
+    http: &Http,
+    board: &db::Board,
+    post: &db::Message,
+) -> Result<(), Error> {
+    let dest_id = MessageId::new(post.dest_id.parse::<u64>()?);
+    let tombstone = CreateEmbed::new()
+        .description("*The original message was deleted*")
+        .color(Color::from_rgb(0x63, 0x63, 0x63));
+
+    if let Some(webhook_url) = &post.sent_webhook_url {
+        // the content of a webhook post is the deleted message itself
+        let (webhook_id, token) = parse_webhook_url(webhook_url)?;
+        let mut edit_message = EditWebhookMessage::new()
+            .content("")
+            .embeds(vec![tombstone]);
+        if let Some(thread_id) = forum_thread(post) {
+            edit_message = edit_message.in_thread(thread_id);
+        }
+
+        if let Err(err) = edit_message
+            .execute(http, (webhook_id, &token, dest_id))
+            .await
+        {
+            forget_deleted_webhook(board, webhook_url, &err)?;
+            return Err(err.into());
+        }
+    } else {
+        http.edit_message(
+            post_channel(board, post)?,
+            dest_id,
+            &EditMessage::new().embeds(vec![tombstone]),
+            Vec::new(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+// delete a board post, its discussion thread is archived and locked rather than deleted,
+// forum posts are deleted along with their thread
+pub async fn delete_post(http: &Http, board: &db::Board, post: &db::Message) -> Result<(), Error> {
+    if let Some(thread_id) = forum_thread(post) {
+        thread_id.delete(http).await?;
+        return Ok(());
+    }
+
+    let dest_id = MessageId::new(post.dest_id.parse::<u64>()?);
+
+    if let Some(webhook_url) = &post.sent_webhook_url {
+        let (webhook_id, token) = parse_webhook_url(webhook_url)?;
+        if let Err(err) = http
+            .delete_webhook_message(webhook_id, None, &token, dest_id)
+            .await
+        {
+            // the bot can still delete the posts of a webhook that was deleted
+            if !forget_deleted_webhook(board, webhook_url, &err)? {
+                return Err(err.into());
+            }
+
+            http.delete_message(post_channel(board, post)?, dest_id, None)
+                .await?;
+        }
+    } else {
+        let dest_channel = ChannelId::new(board.dest_channel.parse::<u64>()?);
+        http.delete_message(dest_channel, dest_id, None).await?;
+    }
+
+    if let Some(thread_id) = &post.thread_id {
+        ChannelId::new(thread_id.parse::<u64>()?)
+            .edit_thread(http, EditThread::new().archived(true).locked(true))
+            .await?;
+    }
+
+    Ok(())
+}
+
+// open a discussion thread on a new board post
+pub async fn open_thread(
+    http: &Http,
+    board: &db::Board,
+    dest_id: MessageId,
+    message: &Message,
+) -> Result<ChannelId, Error> {
+    let dest_channel = ChannelId::new(board.dest_channel.parse::<u64>()?);
+    let thread = dest_channel
+        .create_thread_from_message(
+            http,
+            dest_id,
+            CreateThread::new(render::create_thread_name(message)),
+        )
+        .await?;
+
+    Ok(thread.id)
+}
+
+// URL of the board's webhook, creating one in the destination channel if the board has none yet
+async fn board_webhook(http: &Http, board: &db::Board) -> Result<String, Error> {
+    if let Some(url) = &board.webhook_url {
+        return Ok(url.clone());
+    }
+
+    let _guard = WEBHOOK_CREATION.lock().await;
+
+    // another post may have created the webhook while this one waited
+    let url = match db::get_board_by_id(board.board_id)?.webhook_url {
+        Some(url) => url,
+        None => {
+            let dest_channel = ChannelId::new(board.dest_channel.parse::<u64>()?);
+            let url = dest_channel
+                .create_webhook(http, CreateWebhook::new(WEBHOOK_NAME))
+                .await?
+                .url()?;
+
+            db::set_board_webhook(board.board_id, &url)?;
+            url
+        }
+    };
+
+    Ok(url)
+}
+
+// webhook URLs end in /{id}/{token}
+fn parse_webhook_url(url: &str) -> Result<(WebhookId, String), Error> {
+    let (rest, token) = url.rsplit_once('/').ok_or("Invalid webhook URL")?;
+    let (_, id) = rest.rsplit_once('/').ok_or("Invalid webhook URL")?;
+
+    Ok((WebhookId::new(id.parse::<u64>()?), token.to_string()))
+}
+
+// a webhook deleted from the channel is replaced by a new one on the next post, returns whether
+// the error was for a deleted webhook
+fn forget_deleted_webhook(
+    board: &db::Board,
+    webhook_url: &str,
+    err: &serenity::Error,
+) -> Result<bool, Error> {
+    if let serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) = err
+        && response.error.code == UNKNOWN_WEBHOOK
+    {
+        db::forget_board_webhook(board.board_id, webhook_url)?;
+        return Ok(true);
+    }
+
+    Ok(false)
+}
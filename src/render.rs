@@ -0,0 +1,923 @@
+use crate::{Error, db};
+use poise::serenity_prelude::{
+    Attachment, Color, CreateActionRow, CreateAllowedMentions, CreateAttachment, CreateButton,
+    CreateEmbed, CreateEmbedAuthor, CreateMessage, EditMessage, EditWebhookMessage, Embed,
+    ExecuteWebhook, Http, Message, Poll, ReactionType, StickerFormatType,
+};
+use std::iter::once;
+
+pub const DEFAULT_HEADER: &str = "{board} **| {count} Reactions |** {channel} **({author})**";
+
+// default header of boards with tiers, which leads with the emoji of the current tier
+pub const DEFAULT_TIER_HEADER: &str =
+    "{emoji} {board} **| {count} Reactions |** {channel} **({author})**";
+
+// placeholders that can be used in board post header templates
+pub const HEADER_PLACEHOLDERS: [&str; 6] = ["board", "count", "emoji", "channel", "author", "link"];
+
+const DEFAULT_COLOR: Color = Color::from_rgb(0xff, 0xe1, 0x9c);
+
+// discord rejects messages with more embeds or embed text than this
+const MAX_EMBEDS: usize = 10;
+const MAX_EMBED_CHARS: usize = 6000;
+const MAX_DESCRIPTION_CHARS: usize = 4096;
+const MAX_FIELD_CHARS: usize = 1024;
+const MAX_CONTENT_CHARS: usize = 2000;
+
+// most bytes of video re-uploaded with one board post instead of only being linked, discord caps
+// the size of all files of a message together
+const MAX_VIDEO_UPLOAD: u32 = 8 * 1024 * 1024;
+
+// emoji and embed color shown once a board post reaches a reaction count
+pub struct Tier {
+    pub min_count: usize,
+    pub emoji: ReactionType,
+    pub color: Option<Color>,
+}
+
+pub async fn create_board_message(
+    http: &Http,
+    message: &Message,
+    board: &db::Board,
+    count: usize,
+) -> Result<CreateMessage, Error> {
+    let mut dest_message = CreateMessage::new()
+        .content(render_header(board, count, message))
+        .embeds(create_board_embeds(message, board, count, false))
+        .components(create_jump_buttons(message));
+
+    if board.reupload_videos {
+        dest_message = dest_message.add_files(create_video_uploads(http, message).await);
+    }
+
+    Ok(dest_message)
+}
+
+// board post sent through the board's webhook under the name and avatar of the author, with the
+// message content as its own and the header in a trailing embed
+pub async fn create_webhook_message(
+    http: &Http,
+    message: &Message,
+    board: &db::Board,
+    count: usize,
+) -> ExecuteWebhook {
+    let mut webhook_message = ExecuteWebhook::new()
+        .username(webhook_username(message))
+        .avatar_url(message.author.face())
+        .content(truncate(
+            &message.content,
+            MAX_CONTENT_CHARS,
+            Some(&message.link()),
+        ))
+        .embeds(create_board_embeds(message, board, count, true))
+        .components(create_jump_buttons(message))
+        .allowed_mentions(CreateAllowedMentions::new());
+
+    if board.reupload_videos {
+        webhook_message = webhook_message.add_files(create_video_uploads(http, message).await);
+    }
+
+    webhook_message
+}
+
+// re-render a board post, so the header and tier color follow the count and the source message
+pub fn edit_board_message(message: &Message, board: &db::Board, count: usize) -> EditMessage {
+    EditMessage::new()
+        .content(render_header(board, count, message))
+        .embeds(create_board_embeds(message, board, count, false))
+        .components(create_jump_buttons(message))
+}
+
+// re-render a board post sent through the board's webhook
+pub fn edit_webhook_message(
+    message: &Message,
+    board: &db::Board,
+    count: usize,
+) -> EditWebhookMessage {
+    EditWebhookMessage::new()
+        .content(truncate(
+            &message.content,
+            MAX_CONTENT_CHARS,
+            Some(&message.link()),
+        ))
+        .embeds(create_board_embeds(message, board, count, true))
+        .components(create_jump_buttons(message))
+        .allowed_mentions(CreateAllowedMentions::new())
+}
+
+// name of the discussion thread of a board post, spoilers are left out since thread names
+// can't hide them
+pub fn create_thread_name(message: &Message) -> String {
+    let author = message
+        .author
+        .global_name
+        .as_deref()
+        .unwrap_or(&message.author.name);
+
+    let content = redact_spoilers(&message.content);
+    let excerpt = content.lines().map(str::trim).find(|line| !line.is_empty());
+
+    let name = match excerpt {
+        Some(excerpt) => format!("{}: {}", author, excerpt),
+        None => author.to_string(),
+    };
+
+    // thread names are limited to 100 characters
+    truncate(&name, 100, None)
+}
+
+// replace the text between ||spoiler|| markers, for excerpts shown where spoilers don't work
+fn redact_spoilers(text: &str) -> String {
+    text.split("||")
+        .enumerate()
+        .map(|(index, part)| if index % 2 == 1 { "[spoiler]" } else { part })
+        .collect()
+}
+
+// webhook names are limited to 80 characters and can't contain "discord" or "clyde" in any case,
+// those words are broken up with a zero-width space so the name still reads the same
+fn webhook_username(message: &Message) -> String {
+    let mut name = message
+        .author
+        .global_name
+        .clone()
+        .unwrap_or_else(|| message.author.name.clone());
+
+    for forbidden in ["discord", "clyde"] {
+        while let Some(start) = name.to_ascii_lowercase().find(forbidden) {
+            name.insert(start + 1, '\u{200B}');
+        }
+    }
+
+    name.chars().take(80).collect()
+}
+
+// link buttons back to the source message and the message it replies to
+pub fn create_jump_buttons(message: &Message) -> Vec<CreateActionRow> {
+    let mut buttons = vec![CreateButton::new_link(message.link()).label("Jump to message")];
+
+    if let Some(referenced_message) = &message.referenced_message {
+        buttons.push(CreateButton::new_link(referenced_message.link()).label("Jump to reply"));
+    }
+
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+// tiers are validated when they are saved, invalid ones are ignored
+fn current_tier(board: &db::Board, count: usize) -> Option<Tier> {
+    parse_tiers(board.tiers.as_deref().unwrap_or_default())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|tier| tier.min_count <= count)
+        .max_by_key(|tier| tier.min_count)
+}
+
+// parse space-separated tiers in the format count:emoji or count:emoji:#rrggbb
+pub fn parse_tiers(tiers: &str) -> Result<Vec<Tier>, String> {
+    tiers
+        .split_whitespace()
+        .map(|tier| {
+            let (min_count, rest) = tier
+                .split_once(':')
+                .ok_or(format!("Tier '{}' is missing an emoji", tier))?;
+
+            let min_count = min_count
+                .parse::<usize>()
+                .map_err(|_| format!("Tier '{}' has an invalid count", tier))?;
+
+            // custom emojis contain colons themselves, so the color is split off the end
+            let (emoji, color) = match rest.rsplit_once(':') {
+                Some((emoji, color)) if color.starts_with('#') => {
+                    let color = u32::from_str_radix(&color[1..], 16)
+                        .ok()
+                        .filter(|_| color.len() == 7)
+                        .ok_or(format!("Tier '{}' has an invalid color", tier))?;
+                    (emoji, Some(Color::new(color)))
+                }
+                _ => (rest, None),
+            };
+
+            let emoji = ReactionType::try_from(emoji)
+                .map_err(|_| format!("Tier '{}' has an invalid emoji", tier))?;
+
+            Ok(Tier {
+                min_count,
+                emoji,
+                color,
+            })
+        })
+        .collect()
+}
+
+// render the header of a board post from the board's template, used both when creating
+// and when editing a board post
+pub fn render_header(board: &db::Board, count: usize, message: &Message) -> String {
+    let emoji = match current_tier(board, count) {
+        Some(tier) => tier.emoji.to_string(),
+        None => db::from_csv(board.reactions.clone())
+            .first()
+            .map(ReactionType::to_string)
+            .unwrap_or_default(),
+    };
+
+    let values = [
+        board.name.clone(),
+        count.to_string(),
+        emoji,
+        format!("<#{}>", message.channel_id),
+        message.author.to_string(),
+        message.link(),
+    ];
+
+    let template = board.header.as_deref().unwrap_or(default_header(board));
+
+    // templates are validated when they are saved, fall back to the default if one still fails
+    fill_template(template, &values)
+        .or_else(|_| fill_template(default_header(board), &values))
+        .unwrap_or_default()
+}
+
+// header template of boards without a template of their own
+pub fn default_header(board: &db::Board) -> &'static str {
+    match board.tiers.as_deref() {
+        Some(tiers) if !tiers.is_empty() => DEFAULT_TIER_HEADER,
+        _ => DEFAULT_HEADER,
+    }
+}
+
+// check that a header template only uses known placeholders and fits in a message
+pub fn validate_header(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("The header can't be empty".to_string());
+    }
+
+    let longest = HEADER_PLACEHOLDERS.map(|_| "x".repeat(200));
+    let rendered = fill_template(template, &longest)?;
+
+    if rendered.chars().count() > 2000 {
+        return Err("The header is too long".to_string());
+    }
+
+    Ok(())
+}
+
+// replace each {placeholder} in the template with the value at the same index in
+// HEADER_PLACEHOLDERS
+fn fill_template(template: &str, values: &[String]) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or("Unclosed '{' in header")?;
+
+        let name = &rest[start + 1..end];
+        let index = HEADER_PLACEHOLDERS
+            .iter()
+            .position(|placeholder| *placeholder == name)
+            .ok_or(format!("Unknown placeholder {{{}}}", name))?;
+
+        rendered.push_str(&values[index]);
+        rest = &rest[end + 1..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+// build the embeds of a board post from the source message and the message it replies to,
+// within the embed limits of a single discord message, posts sent through a webhook already
+// show the author and content so their main embed only carries the header and goes last
+fn create_board_embeds(
+    message: &Message,
+    board: &db::Board,
+    count: usize,
+    webhook: bool,
+) -> Vec<CreateEmbed> {
+    let color = current_tier(board, count)
+        .and_then(|tier| tier.color)
+        .unwrap_or(DEFAULT_COLOR);
+
+    let mut budget = EmbedBudget {
+        embeds: MAX_EMBEDS,
+        chars: MAX_EMBED_CHARS,
+    };
+
+    let mut main = if webhook {
+        MessageParts::new(message, None, render_header(board, count, message))
+    } else {
+        MessageParts::new(
+            message,
+            Some(message.author.name.clone()),
+            message.content.clone(),
+        )
+    };
+    let reply = message
+        .referenced_message
+        .as_deref()
+        .map(|referenced_message| {
+            MessageParts::new(
+                referenced_message,
+                Some(format!("Replying to {}", referenced_message.author.name)),
+                referenced_message.content.clone(),
+            )
+        });
+
+    // the main message always gets its embed, the reply only if there is room left
+    budget.take(main.fixed_chars());
+    let mut reply = reply.filter(|reply| budget.take(reply.fixed_chars()));
+
+    // descriptions get the text room the embeds themselves leave, the main message first
+    for parts in once(&mut main).chain(reply.as_mut()) {
+        parts.description = truncate(
+            &parts.text,
+            budget.chars.min(MAX_DESCRIPTION_CHARS),
+            Some(&parts.message.link()),
+        );
+        budget.chars -= parts.description.chars().count();
+    }
+
+    // images that don't get an embed of their own are listed with the other attachments
+    for parts in once(&mut main).chain(reply.as_mut()) {
+        let extra_images = parts.images.split_off(parts.images.len().min(1));
+        for image in extra_images {
+            if budget.take(0) {
+                parts.images.push(image);
+            } else {
+                parts.files.push(image);
+            }
+        }
+    }
+
+    // source embeds like link previews come last, the link is in the content anyway
+    for parts in once(&mut main).chain(reply.as_mut()) {
+        parts.embeds.retain(|embed| budget.take(embed_chars(embed)));
+    }
+
+    let mut embeds = Vec::new();
+    if let Some(reply) = reply {
+        embeds.extend(reply.into_embeds(None));
+    }
+
+    let mut main_embeds = main.into_embeds(Some(color));
+    if webhook {
+        main_embeds.rotate_left(1);
+    }
+    embeds.extend(main_embeds);
+
+    embeds
+}
+
+// room left on a board post for more embeds
+struct EmbedBudget {
+    embeds: usize,
+    chars: usize,
+}
+
+impl EmbedBudget {
+    // reserve an embed with this much text, returns false without reserving if it doesn't fit
+    fn take(&mut self, chars: usize) -> bool {
+        if self.embeds == 0 || chars > self.chars {
+            return false;
+        }
+
+        self.embeds -= 1;
+        self.chars -= chars;
+        true
+    }
+}
+
+// the parts of a message that make it onto a board post
+struct MessageParts<'a> {
+    message: &'a Message,
+    author: Option<String>,
+    // text shown in the description, cut down to fit
+    text: String,
+    description: String,
+    // the first image is shown on the message embed, the others get embeds of their own
+    images: Vec<&'a Attachment>,
+    // listed as links, spoilered images included so they stay hidden
+    files: Vec<&'a Attachment>,
+    embeds: Vec<Embed>,
+}
+
+impl<'a> MessageParts<'a> {
+    fn new(message: &'a Message, author: Option<String>, text: String) -> Self {
+        let (images, files) = message.attachments.iter().partition(|attachment| {
+            attachment_kind(attachment) == AttachmentKind::Image && !is_spoiler(attachment)
+        });
+
+        let embeds = message
+            .embeds
+            .iter()
+            .filter(|embed| !is_spoilered_embed(&message.content, embed))
+            .cloned()
+            .collect();
+
+        Self {
+            message,
+            author,
+            text,
+            description: String::new(),
+            images,
+            files,
+            embeds,
+        }
+    }
+
+    // text of the message embed apart from its description, room for the attachment links
+    // is held back whenever there could be any
+    fn fixed_chars(&self) -> usize {
+        let mut chars = self
+            .author
+            .as_ref()
+            .map_or(0, |author| author.chars().count());
+
+        if let Some(sticker) = self.message.sticker_items.first() {
+            chars += "Sticker".len() + sticker.name.chars().count();
+        }
+        if let Some(poll) = &self.message.poll {
+            chars += "Poll".len() + render_poll(poll).chars().count();
+        }
+        if !self.files.is_empty() || self.images.len() > 1 {
+            chars += "Attachments".len() + MAX_FIELD_CHARS;
+        }
+
+        chars
+    }
+
+    fn into_embeds(self, color: Option<Color>) -> Vec<CreateEmbed> {
+        let mut embed = CreateEmbed::new()
+            .description(self.description)
+            .timestamp(self.message.timestamp);
+
+        if let Some(author) = self.author {
+            embed = embed.author(
+                CreateEmbedAuthor::new(author)
+                    .url(self.message.link())
+                    .icon_url(self.message.author.avatar_url().unwrap_or_default()),
+            );
+        }
+
+        if let Some(color) = color {
+            embed = embed.color(color);
+        }
+
+        embed = add_message_extras(embed, self.message);
+
+        let mut images = self.images.into_iter();
+        if let Some(image) = images.next() {
+            embed = embed.image(&image.url);
+        }
+
+        if !self.files.is_empty() {
+            embed = embed.field("Attachments", render_attachment_links(&self.files), false);
+        }
+
+        let mut embeds = vec![embed];
+        embeds.extend(images.map(image_embed));
+        embeds.extend(
+            self.embeds
+                .into_iter()
+                .map(|embed| CreateEmbed::from(embed).color(Color::from_rgb(0x63, 0x63, 0xff))),
+        );
+
+        embeds
+    }
+}
+
+// text of a source embed that counts towards the embed limit
+fn embed_chars(embed: &Embed) -> usize {
+    [
+        embed.title.as_deref(),
+        embed.description.as_deref(),
+        embed.author.as_ref().map(|author| author.name.as_str()),
+        embed.footer.as_ref().map(|footer| footer.text.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(
+        embed
+            .fields
+            .iter()
+            .flat_map(|field| [field.name.as_str(), field.value.as_str()]),
+    )
+    .map(|text| text.chars().count())
+    .sum()
+}
+
+// cut text down to at most max characters, ending it with an ellipsis and a link to the full
+// message if there is one
+fn truncate(text: &str, max: usize, link: Option<&str>) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let suffix = match link {
+        Some(link) => format!("… [Read more]({})", link),
+        None => "…".to_string(),
+    };
+
+    // two characters are kept free to close a spoiler the cut ends up in
+    let Some(keep) = max.checked_sub(suffix.chars().count() + 2) else {
+        return String::new();
+    };
+
+    let mut truncated: String = text.chars().take(keep).collect();
+    if truncated.matches("||").count() % 2 == 1 {
+        truncated.push_str("||");
+    }
+    truncated.push_str(&suffix);
+    truncated
+}
+
+// download the small videos of a message so they play inline on the board, as many as fit in
+// the upload limit of a message
+pub async fn create_video_uploads(http: &Http, message: &Message) -> Vec<CreateAttachment> {
+    let mut uploads = Vec::new();
+    let mut remaining = MAX_VIDEO_UPLOAD;
+
+    for video in message
+        .attachments
+        .iter()
+        .filter(|attachment| attachment_kind(attachment) == AttachmentKind::Video)
+    {
+        if video.size > remaining {
+            continue;
+        }
+
+        match CreateAttachment::url(http, &video.url).await {
+            Ok(mut upload) => {
+                upload.filename = video.filename.clone();
+                remaining -= video.size;
+                uploads.push(upload);
+            }
+            Err(err) => println!("Error downloading video: {}", err),
+        }
+    }
+
+    uploads
+}
+
+#[derive(PartialEq)]
+enum AttachmentKind {
+    Image,
+    Video,
+    File,
+}
+
+fn attachment_kind(attachment: &Attachment) -> AttachmentKind {
+    let content_type = attachment.content_type.as_deref().unwrap_or_default();
+    let extension = attachment
+        .filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+
+    if content_type.starts_with("image/")
+        || ["png", "jpg", "jpeg", "gif", "webp"].contains(&extension.as_str())
+    {
+        AttachmentKind::Image
+    } else if content_type.starts_with("video/")
+        || ["mp4", "webm", "mov"].contains(&extension.as_str())
+    {
+        AttachmentKind::Video
+    } else {
+        AttachmentKind::File
+    }
+}
+
+// spoilered attachments are uploaded with a SPOILER_ filename prefix
+fn is_spoiler(attachment: &Attachment) -> bool {
+    attachment.filename.starts_with("SPOILER_")
+}
+
+// link previews of urls inside ||spoiler|| markers would reveal them on the board
+fn is_spoilered_embed(content: &str, embed: &Embed) -> bool {
+    embed.url.as_deref().is_some_and(|url| {
+        content
+            .split("||")
+            .skip(1)
+            .step_by(2)
+            .any(|spoiler| spoiler.contains(url))
+    })
+}
+
+// named links to attachments, as many as fit in an embed field
+fn render_attachment_links(files: &[&Attachment]) -> String {
+    let mut links = String::new();
+
+    for (index, file) in files.iter().enumerate() {
+        // only voice messages carry a duration
+        let name = match file.duration_secs {
+            Some(duration) => format!("Voice message ({})", format_duration(duration)),
+            None => file.filename.clone(),
+        };
+
+        let link = if is_spoiler(file) {
+            format!("||[{}]({})||\n", name, file.url)
+        } else {
+            format!("[{}]({})\n", name, file.url)
+        };
+
+        // keep room to mention the attachments that don't fit
+        if links.chars().count() + link.chars().count() + 16 > MAX_FIELD_CHARS {
+            links.push_str(&format!("and {} more", files.len() - index));
+            break;
+        }
+        links.push_str(&link);
+    }
+
+    links
+}
+
+// stickers and polls have no content of their own, show them on the embed instead,
+// an image attachment replaces the sticker image
+fn add_message_extras(mut embed: CreateEmbed, message: &Message) -> CreateEmbed {
+    if let Some(sticker) = message.sticker_items.first() {
+        // lottie stickers are json animations and can't be shown in an embed
+        if sticker.format_type != StickerFormatType::Lottie
+            && let Some(url) = sticker.image_url()
+        {
+            embed = embed.image(url);
+        }
+        embed = embed.field("Sticker", &sticker.name, true);
+    }
+
+    if let Some(poll) = &message.poll {
+        embed = embed.field("Poll", render_poll(poll), false);
+    }
+
+    embed
+}
+
+// poll question followed by each answer with its current number of votes
+fn render_poll(poll: &Poll) -> String {
+    let mut lines = vec![format!(
+        "**{}**",
+        poll.question.text.as_deref().unwrap_or_default()
+    )];
+
+    for answer in &poll.answers {
+        let votes = poll
+            .results
+            .as_ref()
+            .and_then(|results| {
+                results
+                    .answer_counts
+                    .iter()
+                    .find(|answer_count| answer_count.id == answer.answer_id)
+            })
+            .map_or(0, |answer_count| answer_count.count);
+
+        lines.push(format!(
+            "{} — {} {}",
+            answer.poll_media.text.as_deref().unwrap_or_default(),
+            votes,
+            if votes == 1 { "vote" } else { "votes" }
+        ));
+    }
+
+    if poll
+        .results
+        .as_ref()
+        .is_some_and(|results| results.is_finalized)
+    {
+        lines.push("*Final results*".to_string());
+    }
+
+    truncate(&lines.join("\n"), MAX_FIELD_CHARS, None)
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn image_embed(image: &Attachment) -> CreateEmbed {
+    CreateEmbed::new()
+        .image(&image.url)
+        .color(Color::from_rgb(0x1d, 0xa0, 0xf2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn attachment(id: u64, filename: &str) -> Attachment {
+        serde_json::from_value(json!({
+            "id": id.to_string(),
+            "filename": filename,
+            "size": 1024,
+            "url": format!("https://cdn.example.com/{}/{}", id, filename),
+            "proxy_url": format!("https://media.example.com/{}/{}", id, filename),
+        }))
+        .unwrap()
+    }
+
+    fn link_preview(url: &str, description: &str) -> Embed {
+        serde_json::from_value(json!({
+            "type": "link",
+            "url": url,
+            "title": "Preview",
+            "description": description,
+        }))
+        .unwrap()
+    }
+
+    fn message(id: u64, content: &str, images: usize) -> Message {
+        let mut message = Message::default();
+        message.id = id.into();
+        message.channel_id = 1.into();
+        message.guild_id = Some(1.into());
+        message.content = content.to_string();
+        message.attachments = (0..images)
+            .map(|index| attachment(id * 100 + index as u64, "image.png"))
+            .collect();
+        message
+    }
+
+    fn board() -> db::Board {
+        db::Board {
+            name: "stars".to_string(),
+            reactions: "⭐".to_string(),
+            ..Default::default()
+        }
+    }
+
+    // text of a built embed that counts towards the embed limit
+    fn built_embed_chars(embed: &CreateEmbed) -> usize {
+        let embed = serde_json::to_value(embed).unwrap();
+        let text = |value: &Value| value.as_str().map_or(0, |text| text.chars().count());
+
+        text(&embed["title"])
+            + text(&embed["description"])
+            + text(&embed["author"]["name"])
+            + text(&embed["footer"]["text"])
+            + embed["fields"].as_array().map_or(0, |fields| {
+                fields
+                    .iter()
+                    .map(|field| text(&field["name"]) + text(&field["value"]))
+                    .sum()
+            })
+    }
+
+    fn description(embed: &CreateEmbed) -> String {
+        serde_json::to_value(embed).unwrap()["description"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn reply_with_images_and_previews_fits_embed_limits() {
+        let mut reply = message(2, &"r".repeat(3000), 4);
+        reply.embeds = vec![link_preview("https://example.com/a", &"p".repeat(2000))];
+
+        let mut source = message(3, &"s".repeat(5000), 6);
+        source.embeds = (0..4)
+            .map(|index| link_preview(&format!("https://example.com/{}", index), &"p".repeat(1500)))
+            .collect();
+        source.referenced_message = Some(Box::new(reply));
+
+        for webhook in [false, true] {
+            let embeds = create_board_embeds(&source, &board(), 5, webhook);
+
+            assert!(embeds.len() <= MAX_EMBEDS);
+            assert!(embeds.iter().map(built_embed_chars).sum::<usize>() <= MAX_EMBED_CHARS);
+        }
+    }
+
+    #[test]
+    fn images_without_room_are_listed_as_attachments() {
+        let source = message(3, "lots of images", 14);
+        let embeds = create_board_embeds(&source, &board(), 5, false);
+
+        assert_eq!(embeds.len(), MAX_EMBEDS);
+
+        let main = serde_json::to_value(&embeds[0]).unwrap();
+        let links = main["fields"][0]["value"].as_str().unwrap();
+        assert_eq!(links.lines().count(), 14 - MAX_EMBEDS);
+    }
+
+    #[test]
+    fn long_description_ends_with_ellipsis_and_jump_link() {
+        let source = message(3, &"s".repeat(5000), 0);
+        let embeds = create_board_embeds(&source, &board(), 5, false);
+        let description = description(&embeds[0]);
+
+        assert!(description.chars().count() <= MAX_DESCRIPTION_CHARS);
+        assert!(description.ends_with(&format!("… [Read more]({})", source.link())));
+    }
+
+    #[test]
+    fn short_text_is_not_truncated() {
+        assert_eq!(truncate("short", 10, Some("https://link")), "short");
+    }
+
+    #[test]
+    fn truncate_closes_cut_spoilers() {
+        let text = format!("before ||{}|| after", "hidden ".repeat(100));
+
+        for max in [20, 50, 200, 500] {
+            let truncated = truncate(&text, max, Some("https://link"));
+
+            assert!(truncated.chars().count() <= max);
+            assert_eq!(truncated.matches("||").count() % 2, 0, "{}", truncated);
+        }
+    }
+
+    #[test]
+    fn truncate_without_room_for_the_suffix_is_empty() {
+        assert_eq!(truncate("some text", 5, Some("https://link")), "");
+    }
+
+    #[test]
+    fn cut_descriptions_never_leave_spoilers_open() {
+        let source = message(3, &format!("||{}||", "secret ".repeat(1000)), 0);
+        let embeds = create_board_embeds(&source, &board(), 5, false);
+
+        assert_eq!(description(&embeds[0]).matches("||").count() % 2, 0);
+    }
+
+    #[test]
+    fn fill_template_replaces_placeholders() {
+        let values = HEADER_PLACEHOLDERS.map(|placeholder| placeholder.to_uppercase());
+
+        assert_eq!(
+            fill_template("{emoji} {count} in {board} by {author}", &values).unwrap(),
+            "EMOJI COUNT in BOARD by AUTHOR"
+        );
+        assert_eq!(
+            fill_template("no placeholders", &values).unwrap(),
+            "no placeholders"
+        );
+    }
+
+    #[test]
+    fn fill_template_rejects_unknown_and_unclosed_placeholders() {
+        let values = HEADER_PLACEHOLDERS.map(str::to_string);
+
+        assert_eq!(
+            fill_template("{stars}", &values),
+            Err("Unknown placeholder {stars}".to_string())
+        );
+        assert_eq!(
+            fill_template("{count", &values),
+            Err("Unclosed '{' in header".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_header_checks_templates() {
+        assert!(validate_header(DEFAULT_HEADER).is_ok());
+        assert!(validate_header(DEFAULT_TIER_HEADER).is_ok());
+        assert!(validate_header("  ").is_err());
+        assert!(validate_header("{nope}").is_err());
+        assert!(validate_header(&"{board}".repeat(11)).is_err());
+    }
+
+    #[test]
+    fn parse_tiers_reads_counts_emojis_and_colors() {
+        let tiers = parse_tiers("5:⭐ 10:🌟:#ffcc00 25:<:gold:123456789>:#ff0000").unwrap();
+
+        assert_eq!(tiers.len(), 3);
+        assert_eq!(tiers[0].min_count, 5);
+        assert_eq!(tiers[0].emoji, ReactionType::Unicode("⭐".to_string()));
+        assert_eq!(tiers[0].color, None);
+        assert_eq!(tiers[1].color, Some(Color::new(0xffcc00)));
+        assert!(matches!(
+            tiers[2].emoji,
+            ReactionType::Custom { id, .. } if id.get() == 123456789
+        ));
+        assert_eq!(tiers[2].color, Some(Color::new(0xff0000)));
+
+        assert!(parse_tiers("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_tiers_rejects_invalid_tiers() {
+        assert!(parse_tiers("5").is_err());
+        assert!(parse_tiers("five:⭐").is_err());
+        assert!(parse_tiers("5:⭐:#fff").is_err());
+        assert!(parse_tiers("5:⭐:#gggggg").is_err());
+        assert!(parse_tiers("5:not an emoji").is_err());
+    }
+
+    #[test]
+    fn current_tier_is_the_highest_reached() {
+        let board = db::Board {
+            tiers: Some("5:⭐ 10:🌟".to_string()),
+            ..board()
+        };
+
+        assert!(current_tier(&board, 4).is_none());
+        assert_eq!(current_tier(&board, 9).unwrap().min_count, 5);
+        assert_eq!(current_tier(&board, 10).unwrap().min_count, 10);
+    }
+}